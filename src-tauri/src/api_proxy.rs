@@ -80,4 +80,23 @@ pub async fn proxy_api(
     let response_body = response.json::<Value>().await.map_err(|e| e.to_string())?;
 
     Ok(response_body)
-}
\ No newline at end of file
+}
+
+// 以 POST 调用 OpenFrp 接口，flag 不为 true 时返回接口给出的错误信息
+pub async fn call_api(
+    authorization: &str,
+    endpoint: &str,
+    body: Value,
+) -> Result<Value, String> {
+    let response = proxy_api(
+        endpoint.to_string(),
+        "POST".to_string(),
+        Some(serde_json::json!({ "Authorization": authorization })),
+        Some(body),
+    )
+    .await?;
+    if response["flag"].as_bool() != Some(true) {
+        return Err(response["msg"].as_str().unwrap_or("请求失败").to_string());
+    }
+    Ok(response)
+}
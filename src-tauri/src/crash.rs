@@ -0,0 +1,202 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock, TryLockError};
+use tauri::{command, AppHandle, Emitter, Runtime, State};
+
+// 已通知过前端的最后一份崩溃报告时间，保存在 crashes 目录
+const LAST_SEEN_FILE: &str = ".last_seen";
+// 只保留最近的崩溃报告
+const MAX_CRASH_REPORTS: usize = 20;
+
+// 启动时获取的详细系统信息，panic 钩子直接读取，避免崩溃时再启动外部进程
+static SYSTEM_INFO: OnceLock<String> = OnceLock::new();
+// 同一秒内多次崩溃时区分报告文件
+static REPORT_SEQ: AtomicU32 = AtomicU32::new(0);
+
+// 上次运行留下的崩溃报告概要
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashSummary {
+    pub file: String,
+    pub time: u64,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct CrashState(Mutex<Vec<CrashSummary>>);
+
+pub fn crashes_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("crashes")
+}
+
+// 崩溃时应用目录可能尚未初始化，或锁正被发生 panic 的线程持有，此时写入系统临时目录
+fn crash_report_dir() -> PathBuf {
+    let app_dir = match crate::APP_DIR.try_lock() {
+        Ok(guard) => guard.clone(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    match app_dir {
+        Some(app_dir) => crashes_dir(&app_dir),
+        None => std::env::temp_dir().join("openfrp_cpl").join("crashes"),
+    }
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "未知错误".to_string()
+    }
+}
+
+// 在后台获取详细系统信息（Windows 下需要运行 systeminfo，耗时较长），获取完成前崩溃时只记录系统与架构
+pub fn init_system_info() {
+    std::thread::spawn(|| {
+        let _ = SYSTEM_INFO.set(crate::get_detailed_system_info());
+    });
+}
+
+// 安装 panic 钩子：写入崩溃报告后交给默认钩子处理
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let time = crate::session::now_secs();
+        let message = crate::redact::redact_secrets(&panic_message(info));
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or("<unnamed>")
+            .to_string();
+        let backtrace = std::backtrace::Backtrace::force_capture();
+
+        // 构建信息直接取编译期常量，避免在崩溃时再读取配置或启动外部进程
+        let report = format!(
+            "time: {}\nversion: {}\nbuild: {}_{}\nos: {}\nthread: {}\nlocation: {}\nmessage: {}\n\nbacktrace:\n{}\n",
+            time,
+            env!("CARGO_PKG_VERSION"),
+            env!("BUILD_TIME", "未知构建时间"),
+            env!("GIT_HASH", "未知提交"),
+            SYSTEM_INFO
+                .get()
+                .cloned()
+                .unwrap_or_else(crate::get_system_info),
+            thread,
+            location,
+            message,
+            backtrace
+        );
+
+        let dir = crash_report_dir();
+        let path = dir.join(format!(
+            "crash-{}-{}-{}.txt",
+            time,
+            std::process::id(),
+            REPORT_SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, report)) {
+            Ok(_) => log::error!(
+                "程序崩溃: {} ({})，报告已保存到 {:?}",
+                message,
+                location,
+                path
+            ),
+            Err(e) => log::error!("程序崩溃: {} ({})，保存报告失败: {}", message, location, e),
+        }
+
+        default_hook(info);
+    }));
+}
+
+// 报告文件名为 crash-<秒>-<进程 ID>-<序号>.txt，旧版本为 crash-<秒>.txt
+fn report_time(name: &str) -> Option<u64> {
+    name.strip_prefix("crash-")?
+        .strip_suffix(".txt")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+// 列出崩溃报告，按时间从新到旧排列
+pub fn list_reports(app_dir: &Path) -> Vec<(u64, PathBuf)> {
+    let mut reports: Vec<(u64, PathBuf)> = fs::read_dir(crashes_dir(app_dir))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let time = report_time(&entry.file_name().to_string_lossy())?;
+                    Some((time, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    reports.sort_by(|a, b| b.0.cmp(&a.0));
+    reports
+}
+
+fn summarize(time: u64, path: &Path) -> CrashSummary {
+    let message = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("message: ").map(|m| m.to_string()))
+        })
+        .unwrap_or_default();
+    CrashSummary {
+        file: path.to_string_lossy().to_string(),
+        time,
+        message,
+    }
+}
+
+// 启动时收集上次运行以来新增的崩溃报告，并清理过旧的报告
+pub fn collect_previous_crashes(app_dir: &Path) -> CrashState {
+    let dir = crashes_dir(app_dir);
+    let reports = list_reports(app_dir);
+    for (_, path) in reports.iter().skip(MAX_CRASH_REPORTS) {
+        let _ = fs::remove_file(path);
+    }
+
+    let last_seen: u64 = fs::read_to_string(dir.join(LAST_SEEN_FILE))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+    let new: Vec<CrashSummary> = reports
+        .iter()
+        .take(MAX_CRASH_REPORTS)
+        .filter(|(time, _)| *time > last_seen)
+        .map(|(time, path)| summarize(*time, path))
+        .collect();
+
+    if let Some(latest) = new.first() {
+        let _ = fs::write(dir.join(LAST_SEEN_FILE), latest.time.to_string());
+    }
+    CrashState(Mutex::new(new))
+}
+
+// 窗口就绪后通知前端上次运行发生过崩溃
+pub fn notify_previous_crashes<R: Runtime>(app: &AppHandle<R>, state: &CrashState) {
+    let crashes = state.0.lock().unwrap().clone();
+    if !crashes.is_empty() {
+        let _ = app.emit("previous-crash", &crashes);
+    }
+}
+
+// 前端晚于事件加载时主动查询
+#[command]
+pub fn get_previous_crashes(state: State<'_, CrashState>) -> Vec<CrashSummary> {
+    state.0.lock().unwrap().clone()
+}
+
+#[command]
+pub fn dismiss_previous_crashes(state: State<'_, CrashState>) {
+    state.0.lock().unwrap().clear();
+}
@@ -0,0 +1,278 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::redact;
+
+// 深度链接协议名
+pub const SCHEME: &str = "openfrp";
+
+// 登录 code 的最大长度
+const MAX_CODE_LEN: usize = 512;
+
+// openfrp://import 允许携带的参数
+const IMPORT_KEYS: &[&str] = &[
+    "name",
+    "node",
+    "type",
+    "local_ip",
+    "local_port",
+    "remote_port",
+    "domain",
+];
+
+// 解析后的深度链接动作
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    // openfrp://login?code=...
+    Login {
+        code: String,
+    },
+    // openfrp://start?tunnel=<id>，兼容旧版 openfrp://start_proxy?user=&proxy=&name=；
+    // 旧版链接中的用户密钥不再使用（隧道通过当前账户的会话启动），也不会发送给前端
+    Start {
+        tunnel: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    // openfrp://stop?tunnel=<id>
+    Stop {
+        tunnel: u64,
+    },
+    // openfrp://import?name=&node=&type=&local_ip=&local_port=&remote_port=&domain=
+    Import {
+        params: BTreeMap<String, String>,
+    },
+}
+
+// 窗口就绪前收到的链接会先缓存起来
+#[derive(Default)]
+struct DeepLinkInner {
+    ready: bool,
+    pending: Vec<DeepLinkAction>,
+}
+
+#[derive(Default)]
+pub struct DeepLinkState(Mutex<DeepLinkInner>);
+
+fn parse_tunnel_id(value: Option<&String>) -> Result<u64, String> {
+    let value = value.ok_or("缺少隧道 ID")?;
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id > 0)
+        .ok_or_else(|| format!("无效的隧道 ID: {}", value))
+}
+
+fn validate_code(code: Option<&String>) -> Result<String, String> {
+    let code = code.map(|c| c.trim()).unwrap_or("");
+    if code.is_empty() {
+        return Err("缺少登录 code".to_string());
+    }
+    if code.len() > MAX_CODE_LEN
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err("登录 code 格式错误".to_string());
+    }
+    Ok(code.to_string())
+}
+
+fn validate_import(query: BTreeMap<String, String>) -> Result<BTreeMap<String, String>, String> {
+    if query.is_empty() {
+        return Err("导入链接缺少参数".to_string());
+    }
+    for (key, value) in &query {
+        if !IMPORT_KEYS.contains(&key.as_str()) {
+            return Err(format!("导入链接包含不支持的参数: {}", key));
+        }
+        match key.as_str() {
+            "node" => {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("无效的节点 ID: {}", value))?;
+            }
+            "local_port" | "remote_port" => {
+                value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|p| *p > 0)
+                    .ok_or_else(|| format!("无效的端口: {}", value))?;
+            }
+            "type" => {
+                if !["tcp", "udp", "http", "https", "stcp", "xtcp"].contains(&value.as_str()) {
+                    return Err(format!("不支持的隧道类型: {}", value));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(query)
+}
+
+// 解析并校验 openfrp:// 链接
+pub fn parse_deep_link(url: &str) -> Result<DeepLinkAction, String> {
+    let url = reqwest::Url::parse(url.trim()).map_err(|e| format!("链接格式错误: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("不支持的链接协议: {}", url.scheme()));
+    }
+
+    // openfrp://login?... 中 login 会被解析为 host，openfrp:login?... 则为 path
+    let path = url
+        .host_str()
+        .unwrap_or_else(|| url.path())
+        .trim_matches('/')
+        .to_lowercase();
+    let query: BTreeMap<String, String> = url.query_pairs().into_owned().collect();
+
+    match path.as_str() {
+        "login" => Ok(DeepLinkAction::Login {
+            code: validate_code(query.get("code"))?,
+        }),
+        "start" => Ok(DeepLinkAction::Start {
+            tunnel: parse_tunnel_id(query.get("tunnel"))?,
+            name: None,
+        }),
+        "start_proxy" => Ok(DeepLinkAction::Start {
+            tunnel: parse_tunnel_id(query.get("proxy"))?,
+            name: query.get("name").cloned(),
+        }),
+        "stop" => Ok(DeepLinkAction::Stop {
+            tunnel: parse_tunnel_id(query.get("tunnel"))?,
+        }),
+        "import" => Ok(DeepLinkAction::Import {
+            params: validate_import(query)?,
+        }),
+        _ => Err(format!("不支持的链接类型: {}", path)),
+    }
+}
+
+// 分发一个深度链接：校验失败时发送 deep-link-error，窗口未就绪时缓存。
+// 任何网页都能打开 openfrp:// 链接，启动、停止隧道等动作都交给前端由用户确认后执行
+pub fn dispatch<R: Runtime>(app: &AppHandle<R>, url: &str) {
+    let action = match parse_deep_link(url) {
+        Ok(action) => action,
+        Err(e) => {
+            log::warn!("忽略无效的深度链接 {}: {}", redact::redact_secrets(url), e);
+            let _ = app.emit("deep-link-error", e);
+            return;
+        }
+    };
+
+    let state = app.state::<DeepLinkState>();
+    let mut inner = state.0.lock().unwrap();
+    if inner.ready {
+        drop(inner);
+        emit_action(app, &action);
+    } else {
+        inner.pending.push(action);
+    }
+}
+
+fn emit_action<R: Runtime>(app: &AppHandle<R>, action: &DeepLinkAction) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit("deep-link", action);
+}
+
+// 从启动参数中找出深度链接
+pub fn links_from_args(args: &[String]) -> Vec<String> {
+    let prefix = format!("{}:", SCHEME);
+    args.iter()
+        .filter(|arg| arg.to_lowercase().starts_with(&prefix))
+        .cloned()
+        .collect()
+}
+
+// 前端监听器注册完成后调用，返回并清空就绪前缓存的链接
+#[command]
+pub fn deep_link_ready(state: State<'_, DeepLinkState>) -> Vec<DeepLinkAction> {
+    let mut inner = state.0.lock().unwrap();
+    inner.ready = true;
+    std::mem::take(&mut inner.pending)
+}
+
+// 由前端主动提交的链接（例如用户粘贴），走同一套校验
+#[command]
+pub fn parse_deep_link_url(url: String) -> Result<DeepLinkAction, String> {
+    parse_deep_link(&url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_start_and_stop() {
+        assert_eq!(
+            parse_deep_link("openfrp://start?tunnel=42"),
+            Ok(DeepLinkAction::Start {
+                tunnel: 42,
+                name: None
+            })
+        );
+        assert_eq!(
+            parse_deep_link("openfrp://stop/?tunnel=7"),
+            Ok(DeepLinkAction::Stop { tunnel: 7 })
+        );
+        assert!(parse_deep_link("openfrp://start?tunnel=0").is_err());
+        assert!(parse_deep_link("openfrp://stop?tunnel=abc").is_err());
+        assert!(parse_deep_link("openfrp://stop").is_err());
+    }
+
+    #[test]
+    fn legacy_start_drops_token() {
+        let action = parse_deep_link(
+            "openfrp://start_proxy?user=0123456789abcdef0123456789abcdef&proxy=5&name=web",
+        )
+        .unwrap();
+        assert_eq!(
+            action,
+            DeepLinkAction::Start {
+                tunnel: 5,
+                name: Some("web".to_string())
+            }
+        );
+        let json = serde_json::to_string(&action).unwrap();
+        assert!(!json.contains("0123456789abcdef"), "{}", json);
+    }
+
+    #[test]
+    fn parse_login() {
+        assert_eq!(
+            parse_deep_link("openfrp:login?code=abc-123_x.y"),
+            Ok(DeepLinkAction::Login {
+                code: "abc-123_x.y".to_string()
+            })
+        );
+        assert!(parse_deep_link("openfrp://login").is_err());
+        assert!(parse_deep_link("openfrp://login?code=a%20b").is_err());
+    }
+
+    #[test]
+    fn parse_import() {
+        let action = parse_deep_link("openfrp://import?name=mc&type=tcp&local_port=25565").unwrap();
+        match action {
+            DeepLinkAction::Import { params } => {
+                assert_eq!(params.get("local_port").map(String::as_str), Some("25565"));
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(parse_deep_link("openfrp://import").is_err());
+        assert!(parse_deep_link("openfrp://import?local_port=0").is_err());
+        assert!(parse_deep_link("openfrp://import?type=ftp").is_err());
+        assert!(parse_deep_link("openfrp://import?token=x").is_err());
+    }
+
+    #[test]
+    fn reject_other_links() {
+        assert!(parse_deep_link("https://start?tunnel=1").is_err());
+        assert!(parse_deep_link("openfrp://delete?tunnel=1").is_err());
+        assert!(parse_deep_link("not a url").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Manager, Runtime};
+
+use crate::redact;
+
+// 每个隧道保留的最近日志行数
+const MAX_TUNNEL_LOG_LINES: usize = 500;
+// 每个启动器日志文件最多打包的字节数
+const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024;
+// 诊断包中最多附带的崩溃报告数量
+const MAX_CRASH_REPORTS: usize = 5;
+// 连通性检测超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 连通性检测的目标
+const PROBE_TARGETS: &[(&str, &str)] = &[
+    (
+        "api",
+        "https://api.openfrp.net/commonQuery/get?key=software",
+    ),
+    ("oauth", "https://api.openfrp.net/oauth2/login"),
+    ("updater", "https://api.zyghit.cn/updater/ofcpl"),
+];
+
+// 配置中名称包含这些关键字的字段会被遮盖
+const SECRET_KEYS: &[&str] = &["token", "authorization", "password", "secret", "user_key"];
+
+// 各隧道最近的 frpc 输出（已脱敏），用于诊断包
+#[derive(Default)]
+pub struct RecentLogs(Mutex<HashMap<String, VecDeque<String>>>);
+
+impl RecentLogs {
+    pub fn push(&self, id: &str, line: String) {
+        if let Ok(mut logs) = self.0.lock() {
+            let lines = logs.entry(id.to_string()).or_default();
+            if lines.len() >= MAX_TUNNEL_LOG_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    // 某个隧道最近的日志
+    pub fn lines(&self, id: &str) -> Vec<String> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|logs| logs.get(id).map(|lines| lines.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProbeResult {
+    name: String,
+    url: String,
+    ok: bool,
+    status: Option<u16>,
+    latency_ms: Option<u128>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProcessSnapshot {
+    id: String,
+    tunnel_id: String,
+    profile_id: Option<String>,
+    pid: u32,
+    running: bool,
+}
+
+async fn probe(client: &reqwest::Client, name: &str, url: &str) -> ProbeResult {
+    let start = Instant::now();
+    match client.get(url).send().await {
+        Ok(response) => ProbeResult {
+            name: name.to_string(),
+            url: url.to_string(),
+            ok: response.status().is_success(),
+            status: Some(response.status().as_u16()),
+            latency_ms: Some(start.elapsed().as_millis()),
+            error: None,
+        },
+        Err(e) => ProbeResult {
+            name: name.to_string(),
+            url: url.to_string(),
+            ok: false,
+            status: None,
+            latency_ms: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+// 检测 API、更新源以及 frpc 下载源的连通性
+async fn connectivity_report() -> Vec<ProbeResult> {
+    let client = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut results = Vec::new();
+    for (name, url) in PROBE_TARGETS {
+        results.push(probe(&client, name, url).await);
+    }
+
+    // 下载源来自软件信息接口
+    if let Ok(response) = client.get(PROBE_TARGETS[0].1).send().await {
+        if let Ok(info) = response.json::<crate::SoftwareInfo>().await {
+            for source in &info.data.source {
+                let name = format!("download:{}", source.label);
+                results.push(probe(&client, &name, &source.value).await);
+            }
+        }
+    }
+    results
+}
+
+// 运行 frpc -v 获取版本输出
+fn frpc_version_output(app_dir: &Path) -> String {
+    let filename = match crate::load_config().ok().and_then(|c| c.frpc_filename) {
+        Some(filename) if !filename.is_empty() => filename,
+        _ => return "frpc 文件名未配置".to_string(),
+    };
+    let path = app_dir.join(&filename);
+    if !path.exists() {
+        return format!("frpc 不存在: {}", path.display());
+    }
+
+    let mut cmd = Command::new(&path);
+    cmd.arg("-v");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(crate::CREATE_NO_WINDOW);
+    }
+    match cmd.output() {
+        Ok(output) => format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => format!("运行 frpc 失败: {}", e),
+    }
+}
+
+// 遮盖配置中疑似密钥的字段
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_lowercase();
+                if SECRET_KEYS.iter().any(|k| key.contains(k)) {
+                    if let Some(s) = value.as_str() {
+                        *value = serde_json::Value::String(redact::mask_secret(s));
+                        continue;
+                    }
+                }
+                redact_json(value);
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn redacted_config(app_dir: &Path) -> String {
+    let content = match fs::read_to_string(app_dir.join("config.json")) {
+        Ok(content) => content,
+        Err(e) => return format!("读取配置文件失败: {}", e),
+    };
+    match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(mut value) => {
+            redact_json(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        }
+        Err(_) => content,
+    }
+}
+
+fn process_snapshot(processes: &crate::FrpcProcesses) -> Vec<ProcessSnapshot> {
+    let mut map = match processes.0.lock() {
+        Ok(map) => map,
+        Err(_) => return Vec::new(),
+    };
+    map.iter_mut()
+        .map(|(id, info)| ProcessSnapshot {
+            id: id.clone(),
+            tunnel_id: info.tunnel_id.clone(),
+            profile_id: info.profile_id.clone(),
+            pid: info.child.id(),
+            running: matches!(info.child.try_wait(), Ok(None)),
+        })
+        .collect()
+}
+
+// 读取日志文件末尾，过大的文件只保留最后一部分
+fn read_log_tail(path: &Path) -> Option<String> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len > MAX_LOG_FILE_BYTES {
+        file.seek(SeekFrom::Start(len - MAX_LOG_FILE_BYTES)).ok()?;
+    }
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+// 诊断包中需要遮盖的已知密钥：当前会话（用户密钥按特征遮盖）
+fn known_secrets<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    crate::session::current_authorization(app)
+        .into_iter()
+        .collect()
+}
+
+// 导出诊断包，返回生成的 zip 文件路径
+#[command]
+pub async fn export_diagnostics<R: Runtime>(
+    app: AppHandle<R>,
+    path: Option<String>,
+    include_crashes: Option<bool>,
+) -> Result<String, String> {
+    let app_dir = crate::get_app_dir();
+    let target = match path {
+        Some(path) => PathBuf::from(path),
+        None => app_dir
+            .join("diagnostics")
+            .join(format!("diagnostics-{}.zip", crate::session::now_secs())),
+    };
+
+    let connectivity = connectivity_report().await;
+    // systeminfo、frpc -v 需要运行外部进程，放到阻塞线程中执行
+    let blocking_dir = app_dir.clone();
+    let (system_detail, frpc_version) = tokio::task::spawn_blocking(move || {
+        (
+            crate::get_detailed_system_info(),
+            frpc_version_output(&blocking_dir),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut entries: Vec<(String, String)> = vec![
+        (
+            "system.txt".to_string(),
+            format!(
+                "system: {}\ndetail: {}\nlauncher: {}\n",
+                crate::get_system_info(),
+                system_detail,
+                env!("CARGO_PKG_VERSION")
+            ),
+        ),
+        ("build.txt".to_string(), crate::get_build_info()),
+        ("config.json".to_string(), redacted_config(&app_dir)),
+        ("frpc-version.txt".to_string(), frpc_version),
+        (
+            "processes.json".to_string(),
+            serde_json::to_string_pretty(&process_snapshot(&app.state::<crate::FrpcProcesses>()))
+                .unwrap_or_default(),
+        ),
+        (
+            "connectivity.json".to_string(),
+            serde_json::to_string_pretty(&connectivity).unwrap_or_default(),
+        ),
+    ];
+
+    if let Ok(logs) = app.state::<RecentLogs>().0.lock() {
+        for (id, lines) in logs.iter() {
+            // 实例 ID 来自前端，只接受隧道 ID 格式，避免压缩包内出现 ../ 等路径
+            if crate::tunnel_config::validate_tunnel_id(id).is_err() {
+                continue;
+            }
+            let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+            entries.push((format!("tunnels/{}.log", id), lines.join("\n")));
+        }
+    }
+
+    if let Ok(dir) = fs::read_dir(app_dir.join("logs")) {
+        for entry in dir.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.contains(".log") {
+                continue;
+            }
+            if let Some(content) = read_log_tail(&entry.path()) {
+                entries.push((format!("logs/{}", name), content));
+            }
+        }
+    }
+
+    // 默认附带最近的崩溃报告
+    if include_crashes.unwrap_or(true) {
+        for (_, path) in crate::crash::list_reports(&app_dir)
+            .into_iter()
+            .take(MAX_CRASH_REPORTS)
+        {
+            if let Ok(content) = fs::read_to_string(&path) {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                entries.push((format!("crashes/{}", name), content));
+            }
+        }
+    }
+
+    // 所有内容统一脱敏后再写入
+    let secrets = known_secrets(&app);
+    let secrets: Vec<&str> = secrets.iter().map(|s| s.as_str()).collect();
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建诊断目录失败: {}", e))?;
+    }
+    let file = fs::File::create(&target).map_err(|e| format!("创建诊断包失败: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in entries {
+        zip.start_file(name, options)
+            .map_err(|e| format!("写入诊断包失败: {}", e))?;
+        zip.write_all(redact::redact_known(&content, &secrets).as_bytes())
+            .map_err(|e| format!("写入诊断包失败: {}", e))?;
+    }
+    zip.finish().map_err(|e| format!("写入诊断包失败: {}", e))?;
+
+    Ok(target.to_string_lossy().to_string())
+}
@@ -0,0 +1,525 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::command;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+
+use crate::local_ports::{self, LocalSocket};
+use crate::port_watcher;
+
+const DEFAULT_TIMEOUT_MS: u64 = 1500;
+const MAX_TIMEOUT_MS: u64 = 10000;
+// 连接后等待服务端主动发送欢迎信息的时间
+const BANNER_WAIT: Duration = Duration::from_millis(500);
+const DEFAULT_CONCURRENCY: usize = 8;
+const MAX_CONCURRENCY: usize = 32;
+// 读取 HTTP 响应与 Minecraft 状态的上限
+const MAX_HTTP_BYTES: usize = 16 * 1024;
+const MAX_STATUS_BYTES: usize = 256 * 1024;
+const MAX_BANNER_LEN: usize = 200;
+
+// RDP X.224 Connection Request，附带 RDP_NEG_REQ（请求 TLS/CredSSP）
+const RDP_CONNECTION_REQUEST: [u8; 19] = [
+    0x03, 0x00, 0x00, 0x13, 0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x03,
+    0x00, 0x00, 0x00,
+];
+
+// 识别出的服务
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "service", rename_all = "snake_case")]
+pub enum ServiceInfo {
+    Http {
+        status: u16,
+        server: Option<String>,
+        title: Option<String>,
+    },
+    // 只接受 TLS 握手的端口，通常是 HTTPS
+    Tls,
+    Ssh {
+        version: String,
+    },
+    Rdp,
+    Mysql {
+        version: Option<String>,
+    },
+    Redis {
+        auth_required: bool,
+    },
+    Minecraft {
+        motd: String,
+        version: Option<String>,
+        protocol: Option<i64>,
+        players_online: Option<i64>,
+        players_max: Option<i64>,
+    },
+    // 未识别但主动发送了欢迎信息
+    Banner {
+        banner: String,
+    },
+}
+
+impl ServiceInfo {
+    fn proxy_type(&self) -> &'static str {
+        match self {
+            ServiceInfo::Http { .. } => "http",
+            ServiceInfo::Tls => "https",
+            _ => "tcp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortFingerprint {
+    pub protocol: &'static str,
+    pub port: u16,
+    // 实际探测的地址
+    pub address: Option<String>,
+    pub sockets: Vec<LocalSocket>,
+    pub service: Option<ServiceInfo>,
+    // 已知端口对应的服务名称
+    pub known_service: Option<String>,
+    pub proxy_type: String,
+}
+
+// 监听在全部地址上的端口通过回环地址探测
+fn probe_addr(socket: &LocalSocket) -> Option<SocketAddr> {
+    let ip: IpAddr = socket.address.parse().ok()?;
+    let ip = match ip {
+        IpAddr::V4(v4) if v4.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(v6) if v6.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    Some(SocketAddr::new(ip, socket.port))
+}
+
+async fn connect(addr: SocketAddr, timeout: Duration) -> Option<TcpStream> {
+    tokio::time::timeout(timeout, TcpStream::connect(addr))
+        .await
+        .ok()?
+        .ok()
+}
+
+// 读取到对端关闭、超时或达到上限为止，done 返回 true 时提前结束
+async fn read_until(
+    stream: &mut TcpStream,
+    limit: usize,
+    timeout: Duration,
+    done: impl Fn(&[u8]) -> bool,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let deadline = tokio::time::Instant::now() + timeout;
+    while data.len() < limit && !done(&data) {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => data.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+    }
+    data
+}
+
+// 发送请求并读取回复，每次探测使用新的连接
+async fn exchange(
+    addr: SocketAddr,
+    request: &[u8],
+    limit: usize,
+    timeout: Duration,
+    done: impl Fn(&[u8]) -> bool,
+) -> Option<Vec<u8>> {
+    let mut stream = connect(addr, timeout).await?;
+    tokio::time::timeout(timeout, stream.write_all(request))
+        .await
+        .ok()?
+        .ok()?;
+    let reply = read_until(&mut stream, limit, timeout, done).await;
+    (!reply.is_empty()).then_some(reply)
+}
+
+fn printable(data: &[u8]) -> String {
+    let text: String = String::from_utf8_lossy(data)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    text.trim().chars().take(MAX_BANNER_LEN).collect()
+}
+
+// MySQL 握手包：3 字节长度、序号 0、协议版本 10 与以 0 结尾的版本号；
+// 拒绝连接时返回 0xff 错误包
+fn parse_mysql(data: &[u8]) -> Option<ServiceInfo> {
+    if data.len() < 5 || data[3] != 0 {
+        return None;
+    }
+    let len = u32::from_le_bytes([data[0], data[1], data[2], 0]) as usize;
+    if len == 0 || len > 1024 {
+        return None;
+    }
+    match data[4] {
+        0x0a => {
+            let rest = &data[5..];
+            let end = rest.iter().position(|b| *b == 0)?;
+            Some(ServiceInfo::Mysql {
+                version: Some(String::from_utf8_lossy(&rest[..end]).to_string()),
+            })
+        }
+        0xff if data.len() >= 7 => Some(ServiceInfo::Mysql { version: None }),
+        _ => None,
+    }
+}
+
+// 服务端先发送的欢迎信息
+pub fn parse_banner(data: &[u8]) -> ServiceInfo {
+    if data.starts_with(b"SSH-") {
+        let line = data.split(|b| *b == b'\n').next().unwrap_or(data);
+        return ServiceInfo::Ssh {
+            version: printable(line),
+        };
+    }
+    if let Some(mysql) = parse_mysql(data) {
+        return mysql;
+    }
+    ServiceInfo::Banner {
+        banner: printable(data),
+    }
+}
+
+fn header_end(data: &[u8]) -> Option<usize> {
+    data.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+fn parse_title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = body[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then(|| title.chars().take(MAX_BANNER_LEN).collect())
+}
+
+fn parse_http(data: &[u8]) -> Option<ServiceInfo> {
+    // 向 TLS 端口发送明文会收到 TLS 警告记录
+    if data.len() >= 3 && data[0] == 0x15 && data[1] == 0x03 {
+        return Some(ServiceInfo::Tls);
+    }
+    if !data.starts_with(b"HTTP/") {
+        return None;
+    }
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.lines();
+    let status = lines
+        .next()?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u16>()
+        .ok()?;
+    let server = lines.take_while(|line| !line.is_empty()).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("server")
+            .then(|| value.trim().to_string())
+    });
+    let title =
+        header_end(data).and_then(|end| parse_title(&String::from_utf8_lossy(&data[end..])));
+    Some(ServiceInfo::Http {
+        status,
+        server,
+        title,
+    })
+}
+
+fn parse_redis(data: &[u8]) -> Option<ServiceInfo> {
+    if data.starts_with(b"+PONG") {
+        Some(ServiceInfo::Redis {
+            auth_required: false,
+        })
+    } else if data.starts_with(b"-NOAUTH") || data.starts_with(b"-DENIED") {
+        Some(ServiceInfo::Redis {
+            auth_required: true,
+        })
+    } else {
+        None
+    }
+}
+
+// X.224 Connection Confirm
+fn parse_rdp(data: &[u8]) -> Option<ServiceInfo> {
+    (data.len() >= 6 && data[0] == 0x03 && data[1] == 0x00 && data[5] == 0xd0)
+        .then_some(ServiceInfo::Rdp)
+}
+
+fn write_varint(out: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            out.push(value as u8);
+            return;
+        }
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<i32> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value as i32);
+        }
+    }
+    None
+}
+
+// 读取长度前缀，负数视为无效
+fn read_length(data: &[u8], pos: &mut usize) -> Option<usize> {
+    usize::try_from(read_varint(data, pos)?).ok()
+}
+
+// 握手包（next state = 1）与状态请求
+fn minecraft_status_request(host: &str, port: u16) -> Vec<u8> {
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    write_varint(&mut handshake, -1);
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+
+    let mut packet = Vec::new();
+    write_varint(&mut packet, handshake.len() as i32);
+    packet.extend_from_slice(&handshake);
+    packet.extend_from_slice(&[0x01, 0x00]);
+    packet
+}
+
+// 状态响应是否已完整接收
+fn minecraft_status_complete(data: &[u8]) -> bool {
+    let mut pos = 0;
+    match read_varint(data, &mut pos) {
+        // 长度为负时不再等待，交给解析时拒绝
+        Some(len) => usize::try_from(len)
+            .ok()
+            .and_then(|len| pos.checked_add(len))
+            .map_or(true, |end| data.len() >= end),
+        None => false,
+    }
+}
+
+// MOTD 可能是字符串或聊天组件，去掉 § 格式代码
+fn flatten_motd(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::String(s) => out.push_str(s),
+        serde_json::Value::Object(map) => {
+            if let Some(text) = map.get("text") {
+                flatten_motd(text, out);
+            }
+            if let Some(serde_json::Value::Array(extra)) = map.get("extra") {
+                extra.iter().for_each(|v| flatten_motd(v, out));
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|v| flatten_motd(v, out)),
+        _ => {}
+    }
+}
+
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out.trim().to_string()
+}
+
+fn parse_minecraft(data: &[u8]) -> Option<ServiceInfo> {
+    let mut pos = 0;
+    read_varint(data, &mut pos)?;
+    if read_varint(data, &mut pos)? != 0x00 {
+        return None;
+    }
+    let len = read_length(data, &mut pos)?;
+    let json: serde_json::Value =
+        serde_json::from_slice(data.get(pos..pos.checked_add(len)?)?).ok()?;
+
+    let mut motd = String::new();
+    if let Some(description) = json.get("description") {
+        flatten_motd(description, &mut motd);
+    }
+    let version = &json["version"];
+    let players = &json["players"];
+    Some(ServiceInfo::Minecraft {
+        motd: strip_formatting(&motd),
+        version: version["name"].as_str().map(|s| s.to_string()),
+        protocol: version["protocol"].as_i64(),
+        players_online: players["online"].as_i64(),
+        players_max: players["max"].as_i64(),
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Probe {
+    Http,
+    Minecraft,
+    Redis,
+    Rdp,
+}
+
+// 已知端口优先使用对应协议，减少无关探测
+fn probe_order(port: u16) -> [Probe; 4] {
+    match port {
+        25565 => [Probe::Minecraft, Probe::Http, Probe::Redis, Probe::Rdp],
+        6379 => [Probe::Redis, Probe::Http, Probe::Minecraft, Probe::Rdp],
+        3389 => [Probe::Rdp, Probe::Http, Probe::Minecraft, Probe::Redis],
+        _ => [Probe::Http, Probe::Minecraft, Probe::Redis, Probe::Rdp],
+    }
+}
+
+async fn run_probe(addr: SocketAddr, probe: Probe, timeout: Duration) -> Option<ServiceInfo> {
+    let host = addr.ip().to_string();
+    match probe {
+        Probe::Http => {
+            let request = format!(
+                "GET / HTTP/1.0\r\nHost: {}:{}\r\nUser-Agent: OpenFrp-Launcher\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+                if addr.is_ipv6() { format!("[{}]", host) } else { host },
+                addr.port()
+            );
+            let reply =
+                exchange(addr, request.as_bytes(), MAX_HTTP_BYTES, timeout, |_| false).await?;
+            parse_http(&reply)
+        }
+        Probe::Minecraft => {
+            let reply = exchange(
+                addr,
+                &minecraft_status_request(&host, addr.port()),
+                MAX_STATUS_BYTES,
+                timeout,
+                minecraft_status_complete,
+            )
+            .await?;
+            parse_minecraft(&reply)
+        }
+        Probe::Redis => {
+            let reply = exchange(addr, b"PING\r\n", 256, timeout, |d| d.contains(&b'\n')).await?;
+            parse_redis(&reply)
+        }
+        Probe::Rdp => {
+            let reply = exchange(addr, &RDP_CONNECTION_REQUEST, 256, timeout, |d| {
+                d.len() >= 11
+            })
+            .await?;
+            parse_rdp(&reply)
+        }
+    }
+}
+
+// 识别 TCP 端口上的服务：先等待欢迎信息（SSH、MySQL 等），再依次尝试各协议
+pub async fn identify_tcp(addr: SocketAddr, timeout: Duration) -> Option<ServiceInfo> {
+    let mut stream = connect(addr, timeout).await?;
+    let banner = read_until(&mut stream, 1024, BANNER_WAIT, |d| !d.is_empty()).await;
+    drop(stream);
+    if !banner.is_empty() {
+        return Some(parse_banner(&banner));
+    }
+
+    for probe in probe_order(addr.port()) {
+        if let Some(service) = run_probe(addr, probe, timeout).await {
+            return Some(service);
+        }
+    }
+    None
+}
+
+async fn fingerprint(
+    protocol: &'static str,
+    port: u16,
+    sockets: Vec<LocalSocket>,
+    timeout: Duration,
+) -> PortFingerprint {
+    let suggestion = port_watcher::suggest(protocol, port);
+    let addr = sockets.iter().find_map(probe_addr);
+    let service = match (protocol, addr) {
+        ("tcp", Some(addr)) => identify_tcp(addr, timeout).await,
+        _ => None,
+    };
+    let proxy_type = match &service {
+        Some(service) => service.proxy_type().to_string(),
+        None => suggestion.proxy_type,
+    };
+    PortFingerprint {
+        protocol,
+        port,
+        address: addr.map(|a| a.to_string()),
+        sockets,
+        service,
+        known_service: suggestion.service,
+        proxy_type,
+    }
+}
+
+// 识别本机监听端口上的服务并给出建议的隧道类型，ports 为空时识别全部监听端口
+#[command]
+pub async fn fingerprint_local_ports(
+    ports: Option<Vec<u16>>,
+    timeout_ms: Option<u64>,
+    concurrency: Option<usize>,
+) -> Result<Vec<PortFingerprint>, String> {
+    let timeout = Duration::from_millis(
+        timeout_ms
+            .unwrap_or(DEFAULT_TIMEOUT_MS)
+            .clamp(100, MAX_TIMEOUT_MS),
+    );
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+
+    let sockets = tokio::task::spawn_blocking(|| local_ports::list_sockets(true))
+        .await
+        .map_err(|e| e.to_string())??;
+    // 未指定或指定空列表时识别全部端口
+    let ports = ports.filter(|ports| !ports.is_empty());
+    // 同一端口的 IPv4/IPv6 套接字合并为一项
+    let mut grouped: BTreeMap<(&'static str, u16), Vec<LocalSocket>> = BTreeMap::new();
+    for socket in sockets {
+        if ports
+            .as_ref()
+            .map_or(true, |ports| ports.contains(&socket.port))
+        {
+            grouped
+                .entry((socket.protocol, socket.port))
+                .or_default()
+                .push(socket);
+        }
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = tokio::task::JoinSet::new();
+    for ((protocol, port), sockets) in grouped {
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            fingerprint(protocol, port, sockets, timeout).await
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(e) => log::warn!("端口识别任务异常: {}", e),
+        }
+    }
+    results.sort_by(|a, b| (a.protocol, a.port).cmp(&(b.protocol, b.port)));
+    Ok(results)
+}
@@ -0,0 +1,462 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+use tokio::sync::oneshot;
+
+use crate::ping::{self, AddressFamily};
+use crate::udp_probe::{self, UdpProbeKind};
+
+// 默认检测间隔与超时
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const MIN_INTERVAL_SECS: u64 = 5;
+const DEFAULT_TIMEOUT_MS: u64 = 3000;
+const MAX_TIMEOUT_MS: u64 = 30000;
+
+// 每次开始监控分配的编号，监控结束时只移除自己登记的条目
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// 本地服务的检测方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HealthCheckKind {
+    // 能建立 TCP 连接即视为正常
+    Tcp,
+    // HTTP GET，未指定期望状态码时 5xx 以下均视为正常
+    Http {
+        path: Option<String>,
+        expected_status: Option<u16>,
+        https: Option<bool>,
+    },
+    // 发送 UDP 探测并等待回复
+    Udp {
+        probe: UdpProbeKind,
+    },
+}
+
+// 本地服务不可用时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDown {
+    // 仅通知前端
+    #[default]
+    Ignore,
+    // 启动前检测失败则拒绝启动
+    RefuseStart,
+    // 运行中检测失败则停止隧道，恢复后自动重新启动
+    Stop,
+}
+
+// 单个隧道的健康检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    pub local_ip: String,
+    pub local_port: u16,
+    pub check: HealthCheckKind,
+    pub interval_secs: Option<u64>,
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub on_down: OnDown,
+}
+
+impl HealthConfig {
+    fn interval(&self) -> Duration {
+        Duration::from_secs(
+            self.interval_secs
+                .unwrap_or(DEFAULT_INTERVAL_SECS)
+                .max(MIN_INTERVAL_SECS),
+        )
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.timeout_ms
+                .unwrap_or(DEFAULT_TIMEOUT_MS)
+                .clamp(1, MAX_TIMEOUT_MS),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub up: bool,
+    pub detail: String,
+    pub latency_ms: Option<f64>,
+    pub checked_at: u64,
+}
+
+// tunnel-health 事件，action 为因健康检查停止、重新启动隧道或重新启动失败时的动作
+#[derive(Debug, Clone, Serialize)]
+struct HealthEvent {
+    id: String,
+    status: HealthStatus,
+    action: Option<&'static str>,
+}
+
+struct HealthEntry {
+    status: Option<HealthStatus>,
+    // 因本地服务不可用而暂停的隧道
+    paused: bool,
+    // 启动该隧道的账户，退出登录时据此取消监控
+    profile_id: Option<String>,
+    // 监控配置，重新启动隧道后据此恢复监控
+    config: HealthConfig,
+    generation: u64,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+// 正在监控的隧道
+#[derive(Default)]
+pub struct HealthState(Mutex<HashMap<String, HealthEntry>>);
+
+async fn check_http(
+    config: &HealthConfig,
+    path: Option<&str>,
+    expected_status: Option<u16>,
+    https: bool,
+) -> Result<(f64, String), String> {
+    let client = reqwest::Client::builder()
+        .timeout(config.timeout())
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let host = if config.local_ip.contains(':') {
+        format!("[{}]", config.local_ip)
+    } else {
+        config.local_ip.clone()
+    };
+    let path = path.unwrap_or("/");
+    let url = format!(
+        "{}://{}:{}{}{}",
+        if https { "https" } else { "http" },
+        host,
+        config.local_port,
+        if path.starts_with('/') { "" } else { "/" },
+        path
+    );
+
+    let start = Instant::now();
+    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let latency = start.elapsed().as_secs_f64() * 1000.0;
+    let status = response.status().as_u16();
+    let ok = match expected_status {
+        Some(expected) => status == expected,
+        None => status < 500,
+    };
+    if ok {
+        Ok((latency, format!("HTTP {}", status)))
+    } else {
+        Err(match expected_status {
+            Some(expected) => format!("HTTP 状态码 {}，期望 {}", status, expected),
+            None => format!("HTTP 状态码 {}", status),
+        })
+    }
+}
+
+async fn run_check(config: &HealthConfig) -> Result<(f64, String), String> {
+    let (addrs, _) = ping::resolve(&config.local_ip, config.local_port, AddressFamily::Any).await?;
+    let addr = addrs[0];
+    match &config.check {
+        HealthCheckKind::Tcp => ping::tcp_connect_once(addr, config.timeout())
+            .await
+            .map(|latency| (latency, format!("{} 可以连接", addr))),
+        HealthCheckKind::Http {
+            path,
+            expected_status,
+            https,
+        } => {
+            check_http(
+                config,
+                path.as_deref(),
+                *expected_status,
+                https.unwrap_or(false),
+            )
+            .await
+        }
+        HealthCheckKind::Udp { probe } => {
+            let payload = udp_probe::raw_payload(probe)?;
+            tokio::time::timeout(
+                config.timeout(),
+                udp_probe::probe_once(addr, probe, &payload),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(format!(
+                    "等待回复超时（{}ms）",
+                    config.timeout().as_millis()
+                ))
+            })
+            .map(|(latency, _)| (latency, format!("{} 有回复", addr)))
+        }
+    }
+}
+
+// 检测一次本地服务
+pub async fn check(config: &HealthConfig) -> HealthStatus {
+    let checked_at = crate::session::now_secs();
+    match run_check(config).await {
+        Ok((latency, detail)) => HealthStatus {
+            up: true,
+            detail,
+            latency_ms: Some(latency),
+            checked_at,
+        },
+        Err(e) => HealthStatus {
+            up: false,
+            detail: format!(
+                "本地服务 {}:{} 不可用: {}",
+                config.local_ip, config.local_port, e
+            ),
+            latency_ms: None,
+            checked_at,
+        },
+    }
+}
+
+fn emit_status<R: Runtime>(
+    app: &AppHandle<R>,
+    id: &str,
+    status: &HealthStatus,
+    action: Option<&'static str>,
+) {
+    let _ = app.emit(
+        "tunnel-health",
+        HealthEvent {
+            id: id.to_string(),
+            status: status.clone(),
+            action,
+        },
+    );
+}
+
+// 更新记录的状态，返回状态是否发生变化
+fn record_status<R: Runtime>(app: &AppHandle<R>, id: &str, status: &HealthStatus) -> bool {
+    let state = app.state::<HealthState>();
+    let mut map = match state.0.lock() {
+        Ok(map) => map,
+        Err(_) => return false,
+    };
+    match map.get_mut(id) {
+        Some(entry) => {
+            let changed = entry.status.as_ref().map(|s| s.up) != Some(status.up);
+            entry.status = Some(status.clone());
+            changed
+        }
+        None => false,
+    }
+}
+
+fn set_paused<R: Runtime>(app: &AppHandle<R>, id: &str, paused: bool) {
+    if let Ok(mut map) = app.state::<HealthState>().0.lock() {
+        if let Some(entry) = map.get_mut(id) {
+            entry.paused = paused;
+        }
+    }
+}
+
+// 开始定期检测隧道的本地服务，initial 为启动前的检测结果
+pub fn watch<R: Runtime>(
+    app: &AppHandle<R>,
+    id: String,
+    config: HealthConfig,
+    initial: Option<HealthStatus>,
+) {
+    let (cancel_tx, mut cancel_rx) = oneshot::channel();
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    let profile_id = app
+        .state::<crate::FrpcProcesses>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|map| map.get(&id).and_then(|info| info.profile_id.clone()));
+    {
+        let state = app.state::<HealthState>();
+        let mut map = match state.0.lock() {
+            Ok(map) => map,
+            Err(_) => return,
+        };
+        if let Some(old) = map.insert(
+            id.clone(),
+            HealthEntry {
+                status: initial.clone(),
+                paused: false,
+                profile_id,
+                config: config.clone(),
+                generation,
+                cancel: Some(cancel_tx),
+            },
+        ) {
+            if let Some(cancel) = old.cancel {
+                let _ = cancel.send(());
+            }
+        }
+    }
+    if let Some(status) = &initial {
+        emit_status(app, &id, status, None);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // 自动停止后用于重新启动隧道的参数
+        let mut paused: Option<(String, Option<String>)> = None;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(config.interval()) => {}
+                _ = &mut cancel_rx => return,
+            }
+
+            // 隧道被用户停止或自行退出后结束监控
+            if paused.is_none() && !crate::tunnel_verify::is_running(&app, &id) {
+                break;
+            }
+
+            let status = check(&config).await;
+            if record_status(&app, &id, &status) {
+                log::info!("隧道 {} 本地服务状态变化: {}", id, status.detail);
+                emit_status(&app, &id, &status, None);
+            }
+
+            if !status.up && paused.is_none() && config.on_down == OnDown::Stop {
+                let processes = app.state::<crate::FrpcProcesses>();
+                let params = processes.0.lock().ok().and_then(|map| {
+                    map.get(&id)
+                        .map(|info| (info.tunnel_id.clone(), info.profile_id.clone()))
+                });
+                if params.is_some() && crate::stop_process(&processes, &id) {
+                    log::warn!("隧道 {} 本地服务不可用，已自动停止", id);
+                    paused = params;
+                    set_paused(&app, &id, true);
+                    emit_status(&app, &id, &status, Some("stopped"));
+                }
+            } else if status.up {
+                if let Some((tunnel_id, profile_id)) = paused.take() {
+                    let processes = app.state::<crate::FrpcProcesses>();
+                    match crate::launch_tunnel(&app, &processes, id.clone(), tunnel_id, profile_id)
+                        .await
+                    {
+                        Ok(_) => {
+                            log::info!("隧道 {} 本地服务已恢复，已重新启动", id);
+                            set_paused(&app, &id, false);
+                            emit_status(&app, &id, &status, Some("restarted"));
+                        }
+                        Err(e) => {
+                            log::error!("隧道 {} 重新启动失败: {}", id, e);
+                            let status = HealthStatus {
+                                detail: format!("本地服务已恢复，但重新启动隧道失败: {}", e),
+                                ..status
+                            };
+                            emit_status(&app, &id, &status, Some("failed"));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // 同一隧道可能已重新开始监控，只移除本次登记的条目
+        if let Ok(mut map) = app.state::<HealthState>().0.lock() {
+            if map
+                .get(&id)
+                .map_or(false, |entry| entry.generation == generation)
+            {
+                map.remove(&id);
+            }
+        }
+    });
+}
+
+// 停止监控隧道，返回隧道是否处于因本地服务不可用而暂停的状态
+pub fn unwatch<R: Runtime>(app: &AppHandle<R>, id: &str) -> bool {
+    let entry = match app.state::<HealthState>().0.lock() {
+        Ok(mut map) => map.remove(id),
+        Err(_) => None,
+    };
+    match entry {
+        Some(entry) => {
+            if let Some(cancel) = entry.cancel {
+                let _ = cancel.send(());
+            }
+            entry.paused
+        }
+        None => false,
+    }
+}
+
+// 隧道当前的健康检查配置
+pub fn watched_config<R: Runtime>(app: &AppHandle<R>, id: &str) -> Option<HealthConfig> {
+    app.state::<HealthState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|map| map.get(id).map(|entry| entry.config.clone()))
+}
+
+// 停止监控指定账户的隧道，包括因本地服务不可用而暂停、没有进程的隧道；
+// paused_only 为 true 时只取消暂停中的隧道，运行中的隧道继续监控
+pub fn unwatch_profile<R: Runtime>(
+    app: &AppHandle<R>,
+    profile_id: Option<&str>,
+    paused_only: bool,
+) {
+    let entries: Vec<HealthEntry> = match app.state::<HealthState>().0.lock() {
+        Ok(mut map) => {
+            let ids: Vec<String> = map
+                .iter()
+                .filter(|(_, entry)| {
+                    entry.profile_id.as_deref() == profile_id && (entry.paused || !paused_only)
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| map.remove(id)).collect()
+        }
+        Err(_) => Vec::new(),
+    };
+    for entry in entries {
+        if let Some(cancel) = entry.cancel {
+            let _ = cancel.send(());
+        }
+    }
+}
+
+// 手动检测一次本地服务，用于编辑隧道时预览
+#[command]
+pub async fn check_local_service(config: HealthConfig) -> HealthStatus {
+    check(&config).await
+}
+
+// 为已在运行的隧道开启健康检查
+#[command]
+pub async fn watch_tunnel_health<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    config: HealthConfig,
+) -> Result<HealthStatus, String> {
+    if !crate::tunnel_verify::is_running(&app, &id) {
+        return Err("隧道未在运行".to_string());
+    }
+    let status = check(&config).await;
+    watch(&app, id, config, Some(status.clone()));
+    Ok(status)
+}
+
+#[command]
+pub fn unwatch_tunnel_health<R: Runtime>(app: AppHandle<R>, id: String) {
+    unwatch(&app, &id);
+}
+
+// 各隧道最近一次检测结果
+#[command]
+pub fn get_tunnel_health(state: State<'_, HealthState>) -> HashMap<String, HealthStatus> {
+    state
+        .0
+        .lock()
+        .map(|map| {
+            map.iter()
+                .filter_map(|(id, entry)| entry.status.clone().map(|s| (id.clone(), s)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,391 @@
+use serde::Serialize;
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::IpAddr;
+#[cfg(not(target_os = "linux"))]
+use std::process::Command;
+use tauri::command;
+
+// 本机的一个套接字
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct LocalSocket {
+    // tcp 或 udp
+    pub protocol: &'static str,
+    pub address: String,
+    pub port: u16,
+    // ipv4 或 ipv6
+    pub family: &'static str,
+    pub state: String,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+    pub exe: Option<String>,
+}
+
+impl LocalSocket {
+    // TCP 处于监听状态，或 UDP 未连接到远端
+    pub fn is_listening(&self) -> bool {
+        matches!(self.state.as_str(), "LISTEN" | "UNCONN")
+    }
+}
+
+fn family_of(addr: &IpAddr) -> &'static str {
+    if addr.is_ipv4() {
+        "ipv4"
+    } else {
+        "ipv6"
+    }
+}
+
+#[cfg(target_os = "linux")]
+const TCP_STATES: &[(&str, &str)] = &[
+    ("01", "ESTABLISHED"),
+    ("02", "SYN_SENT"),
+    ("03", "SYN_RECV"),
+    ("04", "FIN_WAIT1"),
+    ("05", "FIN_WAIT2"),
+    ("06", "TIME_WAIT"),
+    ("07", "CLOSE"),
+    ("08", "CLOSE_WAIT"),
+    ("09", "LAST_ACK"),
+    ("0A", "LISTEN"),
+    ("0B", "CLOSING"),
+];
+
+// 解析 /proc/net 中的十六进制地址，内核按本机字节序逐个 32 位字输出
+#[cfg(target_os = "linux")]
+fn parse_proc_addr(s: &str) -> Option<(IpAddr, u16)> {
+    let (ip, port) = s.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = Vec::with_capacity(16);
+    for i in (0..ip.len()).step_by(8) {
+        let word = u32::from_str_radix(ip.get(i..i + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => IpAddr::from(<[u8; 4]>::try_from(bytes.as_slice()).ok()?),
+        16 => {
+            let v6 = std::net::Ipv6Addr::from(<[u8; 16]>::try_from(bytes.as_slice()).ok()?);
+            // 双栈套接字上的 IPv4 映射地址按 IPv4 显示
+            match v6.to_ipv4_mapped() {
+                Some(v4) => IpAddr::V4(v4),
+                None => IpAddr::V6(v6),
+            }
+        }
+        _ => return None,
+    };
+    Some((ip, port))
+}
+
+// 读取 /proc/net/<file>，返回套接字与对应的 inode
+#[cfg(target_os = "linux")]
+fn read_proc_net(file: &str, protocol: &'static str) -> Vec<(LocalSocket, u64)> {
+    let content = match std::fs::read_to_string(format!("/proc/net/{}", file)) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (ip, port) = parse_proc_addr(parts.get(1)?)?;
+            let st = *parts.get(3)?;
+            let inode = parts.get(9)?.parse::<u64>().ok()?;
+            let state = if protocol == "udp" {
+                // UDP 的 07 表示未连接，即在监听
+                let state = if st == "07" { "UNCONN" } else { "ESTAB" };
+                state.to_string()
+            } else {
+                TCP_STATES
+                    .iter()
+                    .find(|(code, _)| *code == st)
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| st.to_string())
+            };
+            Some((
+                LocalSocket {
+                    protocol,
+                    address: ip.to_string(),
+                    port,
+                    family: family_of(&ip),
+                    state,
+                    pid: None,
+                    process: None,
+                    exe: None,
+                },
+                inode,
+            ))
+        })
+        .collect()
+}
+
+// 遍历 /proc/<pid>/fd 建立 inode 到进程的映射，无权限读取的进程会被跳过
+#[cfg(target_os = "linux")]
+fn socket_owners(inodes: &HashSet<u64>) -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return owners,
+    };
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_string_lossy().parse::<u32>() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+        let fds = match std::fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        for fd in fds.flatten() {
+            let target = match std::fs::read_link(fd.path()) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            let inode = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u64>().ok());
+            if let Some(inode) = inode {
+                if inodes.contains(&inode) {
+                    owners.entry(inode).or_insert(pid);
+                }
+            }
+        }
+        if owners.len() == inodes.len() {
+            break;
+        }
+    }
+    owners
+}
+
+#[cfg(target_os = "linux")]
+fn process_info(pid: u32) -> (Option<String>, Option<String>) {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|s| s.trim().to_string());
+    let exe = std::fs::read_link(format!("/proc/{}/exe", pid))
+        .ok()
+        .map(|p| p.to_string_lossy().to_string());
+    (name, exe)
+}
+
+// resolve_owners 为 false 时跳过遍历 /proc/<pid>/fd，供端口监视器低开销轮询
+#[cfg(target_os = "linux")]
+fn enumerate(resolve_owners: bool) -> Result<Vec<LocalSocket>, String> {
+    let mut sockets = Vec::new();
+    for (file, protocol) in [
+        ("tcp", "tcp"),
+        ("tcp6", "tcp"),
+        ("udp", "udp"),
+        ("udp6", "udp"),
+    ] {
+        sockets.extend(read_proc_net(file, protocol));
+    }
+    if sockets.is_empty() && !std::path::Path::new("/proc/net/tcp").exists() {
+        return Err("无法读取 /proc/net".to_string());
+    }
+    if !resolve_owners {
+        return Ok(sockets.into_iter().map(|(socket, _)| socket).collect());
+    }
+
+    let inodes: HashSet<u64> = sockets
+        .iter()
+        .map(|(_, inode)| *inode)
+        .filter(|inode| *inode != 0)
+        .collect();
+    let owners = socket_owners(&inodes);
+    let mut processes: HashMap<u32, (Option<String>, Option<String>)> = HashMap::new();
+    Ok(sockets
+        .into_iter()
+        .map(|(mut socket, inode)| {
+            if let Some(pid) = owners.get(&inode).copied() {
+                let (name, exe) = processes
+                    .entry(pid)
+                    .or_insert_with(|| process_info(pid))
+                    .clone();
+                socket.pid = Some(pid);
+                socket.process = name;
+                socket.exe = exe;
+            }
+            socket
+        })
+        .collect())
+}
+
+// 解析 netstat 的地址列，IPv6 形如 [::]:80
+#[cfg(not(target_os = "linux"))]
+fn parse_netstat_addr(addr: &str) -> Option<(IpAddr, u16)> {
+    let idx = addr.rfind(|c| c == ':' || c == '.')?;
+    let port = addr.get(idx + 1..)?.parse::<u16>().ok()?;
+    let ip = addr
+        .get(..idx)?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let ip = ip.split('%').next()?;
+    let ip = if ip == "*" { "0.0.0.0" } else { ip };
+    Some((ip.parse().ok()?, port))
+}
+
+#[cfg(target_os = "windows")]
+fn enumerate(resolve_owners: bool) -> Result<Vec<LocalSocket>, String> {
+    use std::collections::HashMap;
+    use std::os::windows::process::CommandExt;
+
+    let output = Command::new("netstat")
+        .args(["-ano"])
+        .creation_flags(crate::CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // 获取进程名，CSV 格式不受进程名中空格影响
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let tasklist = if resolve_owners {
+        Command::new("tasklist")
+            .args(["/fo", "csv", "/nh"])
+            .creation_flags(crate::CREATE_NO_WINDOW)
+            .output()
+            .ok()
+    } else {
+        None
+    };
+    if let Some(tasklist) = tasklist {
+        for line in String::from_utf8_lossy(&tasklist.stdout).lines() {
+            let fields: Vec<&str> = line.split("\",\"").collect();
+            if let (Some(name), Some(pid)) = (fields.first(), fields.get(1)) {
+                if let Ok(pid) = pid.trim_matches('"').parse::<u32>() {
+                    names.insert(pid, name.trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let protocol = match parts.first()?.to_uppercase().as_str() {
+                "TCP" => "tcp",
+                "UDP" => "udp",
+                _ => return None,
+            };
+            let (ip, port) = parse_netstat_addr(parts.get(1)?)?;
+            let (state, pid) = if protocol == "tcp" {
+                let state = match *parts.get(3)? {
+                    "LISTENING" => "LISTEN".to_string(),
+                    "ESTABLISHED" => "ESTABLISHED".to_string(),
+                    other => other.to_string(),
+                };
+                (state, parts.get(4)?.parse::<u32>().ok())
+            } else {
+                ("UNCONN".to_string(), parts.get(3)?.parse::<u32>().ok())
+            };
+            Some(LocalSocket {
+                protocol,
+                address: ip.to_string(),
+                port,
+                family: family_of(&ip),
+                state,
+                pid,
+                process: pid.and_then(|p| names.get(&p).cloned()),
+                exe: None,
+            })
+        })
+        .collect())
+}
+
+// macOS 等系统使用 lsof 的字段输出
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn enumerate(_resolve_owners: bool) -> Result<Vec<LocalSocket>, String> {
+    let output = Command::new("lsof")
+        .args(["-nP", "-i", "-FpcPnT"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let mut sockets = Vec::new();
+    let mut pid = None;
+    let mut process = None;
+    let mut protocol = "tcp";
+    let mut pending: Option<LocalSocket> = None;
+    for line in stdout.lines() {
+        let (tag, value) = match line.chars().next() {
+            Some(tag) => (tag, &line[tag.len_utf8()..]),
+            None => continue,
+        };
+        match tag {
+            'p' => {
+                sockets.extend(pending.take());
+                pid = value.parse::<u32>().ok();
+                process = None;
+            }
+            'c' => process = Some(value.to_string()),
+            'f' => sockets.extend(pending.take()),
+            'P' => protocol = if value == "UDP" { "udp" } else { "tcp" },
+            'n' => {
+                // 已连接的套接字形如 local->remote
+                let (local, connected) = match value.split_once("->") {
+                    Some((local, _)) => (local, true),
+                    None => (value, false),
+                };
+                if let Some((ip, port)) = parse_netstat_addr(local) {
+                    pending = Some(LocalSocket {
+                        protocol,
+                        address: ip.to_string(),
+                        port,
+                        family: family_of(&ip),
+                        state: if protocol == "udp" && !connected {
+                            "UNCONN".to_string()
+                        } else {
+                            "ESTAB".to_string()
+                        },
+                        pid,
+                        process: process.clone(),
+                        exe: None,
+                    });
+                }
+            }
+            'T' => {
+                if let (Some(socket), Some(state)) = (pending.as_mut(), value.strip_prefix("ST=")) {
+                    socket.state = state.to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+    sockets.extend(pending.take());
+    Ok(sockets)
+}
+
+// 枚举本机套接字，listening_only 时只返回监听中的套接字
+pub fn list_sockets(listening_only: bool) -> Result<Vec<LocalSocket>, String> {
+    let mut sockets = enumerate(true)?;
+    if listening_only {
+        sockets.retain(LocalSocket::is_listening);
+    }
+    sockets.sort_by(|a, b| (a.protocol, a.port, &a.address).cmp(&(b.protocol, b.port, &b.address)));
+    sockets.dedup();
+    Ok(sockets)
+}
+
+// 监听中的 (协议, 地址, 端口)，不查询所属进程
+pub fn listening_endpoints() -> Result<HashSet<(&'static str, String, u16)>, String> {
+    Ok(enumerate(false)?
+        .into_iter()
+        .filter(LocalSocket::is_listening)
+        .map(|s| (s.protocol, s.address, s.port))
+        .collect())
+}
+
+// 扫描本机端口，默认只返回监听中的套接字，all 为 true 时包含已建立的连接
+#[command]
+pub async fn get_local_ports(all: Option<bool>) -> Result<serde_json::Value, String> {
+    let sockets = tokio::task::spawn_blocking(move || list_sockets(!all.unwrap_or(false)))
+        .await
+        .map_err(|e| e.to_string())??;
+    let (tcp, udp): (Vec<LocalSocket>, Vec<LocalSocket>) =
+        sockets.into_iter().partition(|s| s.protocol == "tcp");
+    Ok(serde_json::json!({ "tcp": tcp, "udp": udp }))
+}
@@ -0,0 +1,135 @@
+use log::LevelFilter;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tauri::command;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
+
+// 日志文件名（不含扩展名），写入 <app_dir>/logs/launcher.log
+const LOG_FILE_NAME: &str = "launcher";
+// 单个日志文件的最大大小，超过后滚动
+const MAX_LOG_FILE_SIZE: u128 = 2 * 1024 * 1024;
+// 保留的历史日志文件数量
+const MAX_ROTATED_FILES: usize = 5;
+// 读取日志末尾时最多读取的字节数
+const MAX_TAIL_BYTES: u64 = 512 * 1024;
+
+pub const DEFAULT_LEVEL: &str = "info";
+
+pub fn logs_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("logs")
+}
+
+fn log_file_path(app_dir: &Path) -> PathBuf {
+    logs_dir(app_dir).join(format!("{}.log", LOG_FILE_NAME))
+}
+
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.trim().to_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+// 删除超出保留数量的历史日志（launcher_<时间>.log），按修改时间保留最新的几个
+fn prune_rotated_logs(dir: &Path) {
+    let mut rotated: Vec<(std::time::SystemTime, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.starts_with(&format!("{}_", LOG_FILE_NAME)) && name.ends_with(".log")
+            })
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    rotated.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, path) in rotated.into_iter().skip(MAX_ROTATED_FILES) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// 初始化日志：同时输出到控制台和滚动日志文件，级别来自配置
+pub fn init(app: &tauri::App, app_dir: &Path, level: Option<&str>) -> tauri::Result<()> {
+    let dir = logs_dir(app_dir);
+    let _ = fs::create_dir_all(&dir);
+    prune_rotated_logs(&dir);
+
+    app.handle().plugin(
+        tauri_plugin_log::Builder::new()
+            .clear_targets()
+            .targets([
+                Target::new(TargetKind::Stdout),
+                Target::new(TargetKind::Folder {
+                    path: dir,
+                    file_name: Some(LOG_FILE_NAME.to_string()),
+                }),
+            ])
+            // 插件内部不过滤，实际级别由 log::set_max_level 控制，便于运行时调整
+            .level(LevelFilter::Trace)
+            .level_for("reqwest", LevelFilter::Warn)
+            .level_for("hyper", LevelFilter::Warn)
+            .level_for("tao", LevelFilter::Warn)
+            .max_file_size(MAX_LOG_FILE_SIZE)
+            .rotation_strategy(RotationStrategy::KeepAll)
+            .timezone_strategy(TimezoneStrategy::UseLocal)
+            .build(),
+    )?;
+
+    log::set_max_level(level.and_then(parse_level).unwrap_or(LevelFilter::Info));
+    Ok(())
+}
+
+#[command]
+pub fn get_log_level() -> String {
+    log::max_level().to_string().to_lowercase()
+}
+
+// 调整日志级别，立即生效并保存到配置
+#[command]
+pub fn set_log_level(level: String) -> Result<(), String> {
+    let filter = parse_level(&level).ok_or_else(|| format!("不支持的日志级别: {}", level))?;
+    log::set_max_level(filter);
+    log::info!("日志级别已调整为 {}", filter);
+
+    let mut config = crate::load_config()?;
+    config.log_level = Some(filter.to_string().to_lowercase());
+    crate::save_config(&config)
+}
+
+// 读取启动器日志的最后若干行
+#[command]
+pub fn tail_launcher_log(lines: Option<usize>) -> Result<Vec<String>, String> {
+    let lines = lines.unwrap_or(200).max(1);
+    let path = log_file_path(&crate::get_app_dir());
+    let mut file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    let start = len.saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("读取日志失败: {}", e))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)
+        .map_err(|e| format!("读取日志失败: {}", e))?;
+
+    let content = String::from_utf8_lossy(&buf);
+    let mut all: Vec<&str> = content.lines().collect();
+    // 从文件中间开始读取时第一行可能不完整
+    if start > 0 && !all.is_empty() {
+        all.remove(0);
+    }
+    let skip = all.len().saturating_sub(lines);
+    Ok(all[skip..].iter().map(|line| line.to_string()).collect())
+}
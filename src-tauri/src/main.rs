@@ -31,7 +31,27 @@ use crate::update::UpdateInfo;
 use crate::update::download_and_install_update;
 use tauri::Listener;
 mod api_proxy;
+mod crash;
+mod deep_link;
+mod diagnostics;
+mod fingerprint;
+mod health;
+mod local_ports;
+mod logging;
+mod minecraft_lan;
+mod oauth;
+mod ping;
+mod port_conflicts;
+mod port_watcher;
+mod profiles;
+mod redact;
+mod session;
+mod tunnel_config;
+mod tunnel_verify;
+mod udp_probe;
 mod update; // 添加这一行
+mod update_schedule;
+mod version;
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -48,6 +68,12 @@ struct ProcessInfo {
     child: Child,
     #[cfg(target_os = "windows")]
     group_id: u32,
+    // 启动该隧道的账户，切换账户时隧道继续运行
+    profile_id: Option<String>,
+    // 启动参数，更新安装后用于重新启动隧道
+    tunnel_id: String,
+    // 隧道配置文件，含用户密钥，隧道停止后删除
+    config_path: PathBuf,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -74,7 +100,7 @@ struct LogPayload {
 }
 
 // 配置文件版本号，用于管理配置文件升级
-const CONFIG_VERSION: u32 = 1;
+const CONFIG_VERSION: u32 = 5;
 
 #[derive(Serialize, Deserialize, Default)]
 struct Config {
@@ -82,6 +108,10 @@ struct Config {
     frpc_version: Option<String>,
     frpc_filename: Option<String>,
     cpl_version: Option<String>,
+    stop_tunnels_on_logout: Option<bool>, // 退出登录时是否停止所有隧道
+    update_channel: Option<String>,       // 更新通道：stable / beta / nightly
+    log_level: Option<String>,            // 日志级别：off / error / warn / info / debug / trace
+    port_watcher: Option<bool>,           // 是否监视新打开的本地端口
 }
 
 impl Config {
@@ -95,6 +125,30 @@ impl Config {
             self.cpl_version = self.cpl_version.or_else(|| Some("0.6.1".to_string()));
         }
 
+        if current_version < 2 {
+            // 版本1到版本2的升级：新增退出登录行为设置
+            self.stop_tunnels_on_logout = self.stop_tunnels_on_logout.or(Some(false));
+        }
+
+        if current_version < 3 {
+            // 版本2到版本3的升级：新增更新通道，默认正式版
+            self.update_channel = self
+                .update_channel
+                .or_else(|| Some(crate::update::DEFAULT_CHANNEL.to_string()));
+        }
+
+        if current_version < 4 {
+            // 版本3到版本4的升级：新增日志级别
+            self.log_level = self
+                .log_level
+                .or_else(|| Some(crate::logging::DEFAULT_LEVEL.to_string()));
+        }
+
+        if current_version < 5 {
+            // 版本4到版本5的升级：新增本地端口监视器开关，默认关闭
+            self.port_watcher = self.port_watcher.or(Some(false));
+        }
+
         // 更新版本号
         self.config_version = Some(CONFIG_VERSION);
         self
@@ -102,22 +156,41 @@ impl Config {
 }
 
 #[command]
-async fn check_update(_app_handle: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
-    match crate::update::check_update().await {
+async fn check_update(app_handle: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let config = load_config()?;
+    let channel = crate::update::normalize_channel(config.update_channel.as_deref());
+    match crate::update::check_update(&app_handle, channel).await {
         Ok(update) => Ok(update),
         Err(e) => {
-            println!("检查更新失败: {}", e);
+            log::warn!("检查更新失败: {}", e);
             Err(e.to_string())
         }
     }
 }
 
+#[command]
+fn get_update_channel() -> Result<String, String> {
+    let config = load_config()?;
+    Ok(crate::update::normalize_channel(config.update_channel.as_deref()).to_string())
+}
+
+#[command]
+fn set_update_channel(channel: String) -> Result<(), String> {
+    let normalized = crate::update::normalize_channel(Some(&channel));
+    if normalized != channel.trim().to_lowercase() {
+        return Err(format!("不支持的更新通道: {}", channel));
+    }
+    let mut config = load_config()?;
+    config.update_channel = Some(normalized.to_string());
+    save_config(&config)
+}
+
 #[command]
 async fn install_update(app_handle: tauri::AppHandle) -> Result<(), String> {
-    match crate::update::download_and_install_update(app_handle).await {
+    match crate::update::download_and_install_update(app_handle, None).await {
         Ok(_) => Ok(()),
         Err(e) => {
-            println!("安装更新失败: {}", e);
+            log::error!("安装更新失败: {}", e);
             Err(e.to_string())
         }
     }
@@ -149,11 +222,9 @@ fn init_app_directory(app: &tauri::App) -> Result<PathBuf, Box<dyn std::error::E
 
 // 获取程序目录的辅助函数
 fn get_app_dir() -> PathBuf {
-    APP_DIR
-        .lock()
-        .unwrap()
-        .clone()
-        .expect("应用程序目录未初始化")
+    // 先释放锁再 expect，panic 钩子中还需要读取该目录
+    let app_dir = APP_DIR.lock().unwrap().clone();
+    app_dir.expect("应用程序目录未初始化")
 }
 
 // 获取配置文件路径
@@ -180,6 +251,10 @@ fn load_config() -> Result<Config, String> {
         Config {
             config_version: Some(CONFIG_VERSION),
             cpl_version: Some(current_version.clone()),
+            stop_tunnels_on_logout: Some(false),
+            update_channel: Some(crate::update::DEFAULT_CHANNEL.to_string()),
+            log_level: Some(crate::logging::DEFAULT_LEVEL.to_string()),
+            port_watcher: Some(false),
             ..Default::default()
         }
     };
@@ -210,6 +285,24 @@ fn save_config(config: &Config) -> Result<(), String> {
     Ok(())
 }
 
+// 写入包含凭据的文件，在 Unix 上仅允许当前用户读写
+fn write_private_file(path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content)?;
+    file.flush()
+}
+
 #[command]
 async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, String> {
     let os = std::env::consts::OS;
@@ -282,7 +375,7 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
         .map_err(|e| e.to_string())?;
 
         if let Some(current_version) = config.frpc_version.as_ref() {
-            if current_version.as_str() == latest_version {
+            if version::is_up_to_date(current_version, &latest_version) {
                 app.emit(
                     "log",
                     LogPayload {
@@ -495,13 +588,46 @@ async fn start_frpc_instance<R: Runtime>(
     app: tauri::AppHandle<R>,
     processes: State<'_, FrpcProcesses>,
     id: String,
-    token: String,
     tunnel_id: String,
+    health: Option<health::HealthConfig>,
 ) -> Result<String, String> {
-    if let Ok(map) = processes.0.lock() {
-        if map.contains_key(&id) {
-            return Err("该隧道已经在运行中".to_string());
+    // 启动前检测本地服务，按配置决定是否拒绝启动
+    let initial = match &health {
+        Some(config) => {
+            let status = health::check(config).await;
+            if !status.up && config.on_down == health::OnDown::RefuseStart {
+                return Err(format!("{}，已取消启动", status.detail));
+            }
+            Some(status)
         }
+        None => None,
+    };
+
+    let profile_id = profiles::active_profile_id(&app);
+    let result = launch_tunnel(&app, &processes, id.clone(), tunnel_id, profile_id).await?;
+    if let Some(config) = health {
+        health::watch(&app, id, config, initial);
+    }
+    Ok(result)
+}
+
+// 启动一个隧道实例并记录到进程表，profile_id 为启动该隧道的账户。
+// 隧道配置（含用户密钥）由该账户的会话从接口获取，写入仅当前用户可读的文件后以 -c 传给 frpc
+async fn launch_tunnel<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    processes: &FrpcProcesses,
+    id: String,
+    tunnel_id: String,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    let is_running = |processes: &FrpcProcesses| {
+        processes
+            .0
+            .lock()
+            .map_or(false, |map| map.contains_key(&id))
+    };
+    if is_running(processes) {
+        return Err("该隧道已经在运行中".to_string());
     }
 
     let app_dir = get_app_dir();
@@ -512,6 +638,16 @@ async fn start_frpc_instance<R: Runtime>(
         return Err("frpc 程序不存在，请先下载".to_string());
     }
 
+    tunnel_config::validate_tunnel_id(&tunnel_id)?;
+    let authorization =
+        session::profile_authorization(&profiles::profile_dir_of(profile_id.as_deref()))?;
+    let content = tunnel_config::fetch_tunnel_config(&authorization, &tunnel_id).await?;
+    // 获取配置期间可能已从其他入口启动
+    if is_running(processes) {
+        return Err("该隧道已经在运行中".to_string());
+    }
+    let config_path = tunnel_config::write_tunnel_config(&app_dir, &id, &content)?;
+
     let mut cmd = Command::new(frpc_path);
 
     #[cfg(target_os = "windows")]
@@ -519,10 +655,11 @@ async fn start_frpc_instance<R: Runtime>(
         cmd.creation_flags(CREATE_NO_WINDOW);
     }
 
-    cmd.args(&["-u", &token, "-p", &tunnel_id])
+    cmd.arg("-c")
+        .arg(&config_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
-    println!("{:?}", cmd);
+    log::info!("启动隧道 {} (隧道ID: {})", id, tunnel_id);
 
     // 添加绕过系统代理的环境变量
     let bypass_proxy = std::env::var("BYPASS_PROXY").unwrap_or_else(|_| "false".to_string());
@@ -533,27 +670,41 @@ async fn start_frpc_instance<R: Runtime>(
         cmd.env("https_proxy", "");
     }
 
-    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            tunnel_config::remove_tunnel_config(&config_path);
+            return Err(e.to_string());
+        }
+    };
 
-    // 处理标准输出
+    // 处理标准输出，frpc 退出后输出结束，随即删除配置文件
     if let Some(stdout) = child.stdout.take() {
         let event_name = format!("frpc-log-{}", id);
+        let log_id = id.clone();
         let app_handle = app.clone();
+        let config_path = config_path.clone();
 
         std::thread::spawn(move || {
             use std::io::{BufRead, BufReader};
             let reader = BufReader::new(stdout);
             for line in reader.lines() {
                 if let Ok(line) = line {
-                    let _ = app_handle.emit(&event_name, LogPayload { message: line });
+                    let message = redact::redact_secrets(&line);
+                    app_handle
+                        .state::<diagnostics::RecentLogs>()
+                        .push(&log_id, message.clone());
+                    let _ = app_handle.emit(&event_name, LogPayload { message });
                 }
             }
+            tunnel_config::remove_tunnel_config(&config_path);
         });
     }
 
     // 处理标准错误
     if let Some(stderr) = child.stderr.take() {
         let event_name = format!("frpc-log-{}", id);
+        let log_id = id.clone();
         let app_handle = app.clone();
 
         std::thread::spawn(move || {
@@ -561,12 +712,11 @@ async fn start_frpc_instance<R: Runtime>(
             let reader = BufReader::new(stderr);
             for line in reader.lines() {
                 if let Ok(line) = line {
-                    let _ = app_handle.emit(
-                        &event_name,
-                        LogPayload {
-                            message: format!("错误: {}", line),
-                        },
-                    );
+                    let message = format!("错误: {}", redact::redact_secrets(&line));
+                    app_handle
+                        .state::<diagnostics::RecentLogs>()
+                        .push(&log_id, message.clone());
+                    let _ = app_handle.emit(&event_name, LogPayload { message });
                 }
             }
         });
@@ -577,11 +727,28 @@ async fn start_frpc_instance<R: Runtime>(
         #[cfg(target_os = "windows")]
         {
             let group_id = child.id();
-            map.insert(id.clone(), ProcessInfo { child, group_id });
+            map.insert(
+                id.clone(),
+                ProcessInfo {
+                    child,
+                    group_id,
+                    profile_id,
+                    tunnel_id,
+                    config_path,
+                },
+            );
         }
         #[cfg(not(target_os = "windows"))]
         {
-            map.insert(id.clone(), ProcessInfo { child });
+            map.insert(
+                id.clone(),
+                ProcessInfo {
+                    child,
+                    profile_id,
+                    tunnel_id,
+                    config_path,
+                },
+            );
         }
     }
 
@@ -927,90 +1094,55 @@ fn get_macos_version() -> String {
 fn get_build_info() -> String {
     let build_time = env!("BUILD_TIME", "未知构建时间");
     let _commit_id = env!("GIT_HASH", "未知提交");
-    format!("build.{}", build_time)
+    let channel = load_config()
+        .map(|config| crate::update::normalize_channel(config.update_channel.as_deref()))
+        .unwrap_or(crate::update::DEFAULT_CHANNEL);
+    format!("build.{}_{}", build_time, channel)
 }
 
-#[command]
-async fn tcp_ping(host: String, port: u16) -> Result<serde_json::Value, String> {
-    use std::net::{TcpStream, ToSocketAddrs};
-    use std::time::Instant;
-    println!("tcp_ping called with host: {}, port: {}", host, port);
+// 结束 frpc 进程
+fn terminate_process(mut process_info: ProcessInfo) {
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = Command::new("taskkill");
+        cmd.creation_flags(CREATE_NO_WINDOW);
+        let _ = cmd
+            .args(&["/F", "/T", "/PID"])
+            .arg(process_info.group_id.to_string())
+            .output();
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = process_info.child.kill();
+    }
+    tunnel_config::remove_tunnel_config(&process_info.config_path);
+}
 
-    
-    // 解析主机名和端口为SocketAddr
-    let addrs = match (host.as_str(), port).to_socket_addrs() {
-        Ok(addrs) => addrs,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "latency_ms": null,
-                "message": format!("地址解析失败: {}", e)
-            }));
-        }
+// 停止指定实例，返回该实例是否存在
+fn stop_process(processes: &FrpcProcesses, id: &str) -> bool {
+    let process_info = match processes.0.lock() {
+        Ok(mut map) => map.remove(id),
+        Err(_) => None,
     };
-    
-    
-    // 取第一个地址进行连接测试
-   let addr = match addrs.into_iter().next() {
-        Some(addr) => {
-            println!("Using address: {:?}", addr);
-            addr
-        },
-        None => {
-            println!("No valid address found");
-            return Ok(serde_json::json!({
-                "success": false,
-                "latency_ms": null,
-                "message": "未能解析到有效的地址"
-            }));
-        }
-    };
-    
-    let start = Instant::now();
-    match TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(5)) {
-        Ok(_) => {
-            let duration = start.elapsed();
-            println!("Connection successful, latency: {}ms", duration.as_millis());
-            Ok(serde_json::json!({
-                "success": true,
-                "latency_ms": duration.as_millis(),
-                "message": format!("测试连接成功，延迟 {}ms", duration.as_millis())
-            }))
-        },
-        Err(e) => {
-            println!("Connection failed: {}", e);
-            Ok(serde_json::json!({
-                "success": false,
-                "latency_ms": null,
-                "message": format!("发生错误，连接失败: {}", e)
-            }))
+    match process_info {
+        Some(process_info) => {
+            terminate_process(process_info);
+            true
         }
+        None => false,
     }
 }
 
 #[command]
 async fn stop_frpc_instance<R: Runtime>(
-    _app: tauri::AppHandle<R>,
+    app: tauri::AppHandle<R>,
     processes: State<'_, FrpcProcesses>,
     id: String,
 ) -> Result<(), String> {
-    if let Ok(mut map) = processes.0.lock() {
-        if let Some(mut process_info) = map.remove(&id) {
-            #[cfg(target_os = "windows")]
-            {
-                let mut cmd = Command::new("taskkill");
-                cmd.creation_flags(CREATE_NO_WINDOW);
-                let _ = cmd
-                    .args(&["/F", "/T", "/PID"])
-                    .arg(process_info.group_id.to_string())
-                    .output();
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                let _ = process_info.child.kill();
-            }
-            return Ok(());
-        }
+    // 因本地服务不可用而暂停的隧道没有进程，取消监控即视为已停止
+    let paused = health::unwatch(&app, &id);
+    if stop_process(&processes, &id) || paused {
+        return Ok(());
     }
     Err("进程不存在".to_string())
 }
@@ -1319,18 +1451,8 @@ fn create_tray_menu(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Er
             "quit_with_frpc" => {
                 if let Some(processes) = app.try_state::<FrpcProcesses>() {
                     if let Ok(mut map) = processes.0.lock() {
-                        for (_, mut process_info) in map.drain() {
-                            #[cfg(target_os = "windows")]
-                            {
-                                let _ = Command::new("taskkill")
-                                    .args(&["/F", "/T", "/PID"])
-                                    .arg(process_info.group_id.to_string())
-                                    .output();
-                            }
-                            #[cfg(not(target_os = "windows"))]
-                            {
-                                let _ = process_info.child.kill();
-                            }
+                        for (_, process_info) in map.drain() {
+                            terminate_process(process_info);
                         }
                     }
                 }
@@ -1419,51 +1541,16 @@ async fn check_auto_start(app: tauri::AppHandle) -> Result<bool, String> {
     Ok(state1)
 }
 
-// 添加状态结构体
-#[derive(Default)]
-struct DeepLinkState(Mutex<Option<String>>);
-
-#[derive(Serialize, Deserialize)]
-struct OAuthResponse {
-    authorization: String,
-    flag: bool,
-    msg: String,
-    data: String,
-}
-
 #[command]
-async fn oauth_callback(code: String) -> Result<OAuthResponse, String> {
-    let client = reqwest::Client::new();
-    let mut form = std::collections::HashMap::new();
-    form.insert("code", code);
-    form.insert("redirect_url", "https://www.zyghit.cn/ofcpl_login".to_string());
-
-    let res = client
-        .post("https://api.openfrp.net/oauth2/callback")
-        .form(&form)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let headers = res.headers();
-    let auth = headers
-        .get("authorization")
-        .ok_or("登录失败: 未能找到 Authorization")?
-        .to_str()
-        .map_err(|e| e.to_string())?
-        .to_string();
-
-    let json = res
-        .json::<serde_json::Value>()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    Ok(OAuthResponse {
-        authorization: auth,
-        flag: json["flag"].as_bool().unwrap_or(false),
-        msg: json["msg"].as_str().unwrap_or("").to_string(),
-        data: json["data"].as_str().unwrap_or("").to_string(),
-    })
+async fn oauth_callback(
+    app: tauri::AppHandle,
+    code: String,
+) -> Result<oauth::OAuthResponse, String> {
+    let response = oauth::exchange_code(code, oauth::DEFAULT_REDIRECT_URL).await?;
+    if response.flag {
+        session::store_session(&app, session::Session::from_oauth(&response))?;
+    }
+    Ok(response)
 }
 
 #[command]
@@ -1508,109 +1595,11 @@ fn open_app_data_dir() -> Result<(), String> {
     Ok(())
 }
 
-use std::collections::HashSet;
-use std::process::Command as StdCommand;
-
-#[tauri::command]
-async fn get_local_ports() -> Result<serde_json::Value, String> {
-    #[cfg(target_os = "windows")]
-    {
-        use std::collections::HashMap;
-        let output = std::process::Command::new("netstat").args(["-ano"]).creation_flags(CREATE_NO_WINDOW).output().map_err(|e| e.to_string())?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut tcp_ports = Vec::new();
-        let mut udp_ports = Vec::new();
-        let mut pid_map: HashMap<u32, String> = HashMap::new();
-        // 获取进程名
-        if let Ok(tasklist) = std::process::Command::new("tasklist").creation_flags(CREATE_NO_WINDOW).output() {
-            let taskout = String::from_utf8_lossy(&tasklist.stdout);
-            for line in taskout.lines().skip(3) {
-                let parts: Vec<_> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    if let Ok(pid) = parts[1].parse::<u32>() {
-                        pid_map.insert(pid, parts[0].to_string());
-                    }
-                }
-            }
-        }
-        for line in stdout.lines() {
-            let line = line.trim();
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let proto = parts[0].to_uppercase();
-                let addr = parts[1];
-                let pid = parts.last().and_then(|s| s.parse::<u32>().ok());
-                if let Some(idx) = addr.rfind(':') {
-                    if let Ok(port) = addr[idx+1..].parse::<u16>() {
-                        let process = pid.and_then(|p| pid_map.get(&p)).cloned();
-                        let entry = serde_json::json!({
-                            "port": port,
-                            "pid": pid,
-                            "process": process,
-                        });
-                        if proto.starts_with("TCP") {
-                            tcp_ports.push(entry);
-                        } else if proto.starts_with("UDP") {
-                            udp_ports.push(entry);
-                        }
-                    }
-                }
-            }
-        }
-        return Ok(serde_json::json!({ "tcp": tcp_ports, "udp": udp_ports }));
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        use std::collections::HashMap;
-        let output = std::process::Command::new("netstat").args(["-tunlp"]).output().map_err(|e| e.to_string())?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut tcp_ports = Vec::new();
-        let mut udp_ports = Vec::new();
-        let mut pid_map: HashMap<u32, String> = HashMap::new();
-        // 解析 /proc 获取进程名
-        if let Ok(entries) = std::fs::read_dir("/proc") {
-            for entry in entries.flatten() {
-                if let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() {
-                    if let Ok(cmdline) = std::fs::read_to_string(format!("/proc/{}/cmdline", pid)) {
-                        let name = cmdline.split('\0').next().unwrap_or("").split('/').last().unwrap_or("").to_string();
-                        pid_map.insert(pid, name);
-                    }
-                }
-            }
-        }
-        for line in stdout.lines() {
-            let line = line.trim();
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() >= 6 {
-                let proto = parts[0].to_lowercase();
-                let addr = parts[3];
-                let pid_info = parts[6];
-                let pid = pid_info.split('/').next().and_then(|s| s.parse::<u32>().ok());
-                if let Some(idx) = addr.rfind(':') {
-                    if let Ok(port) = addr[idx+1..].parse::<u16>() {
-                        let process = pid.and_then(|p| pid_map.get(&p)).cloned();
-                        let entry = serde_json::json!({
-                            "port": port,
-                            "pid": pid,
-                            "process": process,
-                        });
-                        if proto.starts_with("tcp") {
-                            tcp_ports.push(entry);
-                        } else if proto.starts_with("udp") {
-                            udp_ports.push(entry);
-                        }
-                    }
-                }
-            }
-        }
-        return Ok(serde_json::json!({ "tcp": tcp_ports, "udp": udp_ports }));
-    }
-    #[allow(unreachable_code)]
-    Ok(serde_json::json!({ "tcp": [], "udp": [] }))
-}
-
 // 修改 main 函数
 fn main() {
+    crash::install_panic_hook();
+    crash::init_system_info();
+
     let app = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_http::init())
@@ -1624,18 +1613,61 @@ fn main() {
             Some(vec!["--autostart".into()]),
         ))
         .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
-            println!("新实例参数: {:?}", argv);
+            log::info!("新实例参数: {}", redact::redact_secrets(&format!("{:?}", argv)));
+            // 深度链接由 deep-link 插件转交给 deep_link::dispatch 处理，这里只转发其余参数
+            let links = deep_link::links_from_args(&argv);
+            let argv: Vec<String> = argv.into_iter().filter(|arg| !links.contains(arg)).collect();
             let _ = app.emit("second-instance", argv);
         }))
         .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             let app_dir = init_app_directory(app)?;
-            println!("应用程序目录: {:?}", app_dir);
+            let log_level = load_config().ok().and_then(|config| config.log_level);
+            logging::init(app, &app_dir, log_level.as_deref())?;
+            log::info!("应用程序目录: {:?}", app_dir);
+
+            // 收集上次运行留下的崩溃报告，窗口就绪后通知前端
+            app.manage(crash::collect_previous_crashes(&app_dir));
+
+            // 清理上次运行残留的隧道配置文件，其中含有用户密钥
+            tunnel_config::clean_stale_tunnel_configs(&app_dir);
+
+            // 加载账户列表，会话与隧道缓存按账户分别保存
+            app.manage(profiles::ProfilesState::load(&app_dir));
+
+            // 恢复并验证上次保存的会话，之后持续跟踪有效期
+            tauri::async_runtime::spawn(session::restore_and_validate(app.handle().clone()));
+            session::spawn_expiry_monitor(app.handle().clone());
+
+            // 重新启动更新前运行的隧道，并恢复等待安装的更新
+            tauri::async_runtime::spawn(update_schedule::relaunch_tunnels(app.handle().clone()));
+            tauri::async_runtime::spawn(update_schedule::restore_pending(app.handle().clone()));
+            update_schedule::spawn_scheduler(app.handle().clone());
+
+            // 按配置启动本地端口监视器
+            if load_config().ok().and_then(|config| config.port_watcher) == Some(true) {
+                port_watcher::start(app.handle());
+            }
 
-            #[cfg(any(windows, target_os = "linux"))]
             {
                 use tauri_plugin_deep_link::DeepLinkExt;
-                app.deep_link().register("openfrp")?;
+
+                #[cfg(any(windows, target_os = "linux"))]
+                app.deep_link().register(deep_link::SCHEME)?;
+
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        deep_link::dispatch(&handle, url.as_str());
+                    }
+                });
+
+                // 通过链接冷启动时，链接在启动参数中
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    for url in urls {
+                        deep_link::dispatch(app.handle(), url.as_str());
+                    }
+                }
             }
 
             let _tray = create_tray_menu(app)?;
@@ -1643,21 +1675,31 @@ fn main() {
             #[cfg(target_os = "windows")]
             {
                 if let Err(e) = register_app_for_notifications() {
-                    eprintln!("Failed to register app for notifications: {}", e);
+                    log::error!("Failed to register app for notifications: {}", e);
                 }
             }
 
             app.listen("tauri://update-available", |event| {
-                println!("有更新可用: {}", event.payload());
+                log::info!("有更新可用: {}", event.payload());
             });
 
             app.listen("tauri://update-status", |event| {
-                println!("更新状态: {}", event.payload());
+                log::debug!("更新状态: {}", event.payload());
             });
 
             Ok(())
         })
         .manage(FrpcProcesses::default())
+        .manage(deep_link::DeepLinkState::default())
+        .manage(diagnostics::RecentLogs::default())
+        .manage(health::HealthState::default())
+        .manage(minecraft_lan::MinecraftLanState::default())
+        .manage(oauth::OAuthLoopbackState::default())
+        .manage(ping::NodeRankCache::default())
+        .manage(port_watcher::PortWatcherState::default())
+        .manage(session::SessionState::default())
+        .manage(update::UpdateState::default())
+        .manage(update_schedule::ScheduledUpdateState::default())
         .invoke_handler(tauri::generate_handler![
             check_frpc_status,
             download_frpc,
@@ -1676,14 +1718,63 @@ fn main() {
             check_update,
             install_update,
             get_build_info,
+            get_update_channel,
+            set_update_channel,
             get_system_info,
             get_detailed_system_info,
             api_proxy::proxy_api,
             get_app_data_dir,
             open_app_data_dir,
-            get_local_ports, // 新增端口扫描命令
+            local_ports::get_local_ports,
+            fingerprint::fingerprint_local_ports,
+            port_conflicts::check_port_conflicts,
+            minecraft_lan::start_minecraft_lan_listener,
+            minecraft_lan::stop_minecraft_lan_listener,
+            minecraft_lan::set_minecraft_lan_tunnel,
+            minecraft_lan::get_minecraft_lan_worlds,
+            port_watcher::get_port_watcher,
+            port_watcher::set_port_watcher,
+            port_watcher::get_opened_ports,
+            port_watcher::dismiss_opened_port,
             download_and_install_update,
-            tcp_ping,
+            update::get_update_state,
+            update::cancel_update_download,
+            update_schedule::schedule_update,
+            update_schedule::get_pending_update,
+            update_schedule::reschedule_update,
+            update_schedule::cancel_pending_update,
+            ping::tcp_ping,
+            ping::rank_nodes,
+            udp_probe::udp_probe,
+            tunnel_verify::verify_tunnel,
+            health::check_local_service,
+            health::watch_tunnel_health,
+            health::unwatch_tunnel_health,
+            health::get_tunnel_health,
+            deep_link::deep_link_ready,
+            deep_link::parse_deep_link_url,
+            diagnostics::export_diagnostics,
+            crash::get_previous_crashes,
+            crash::dismiss_previous_crashes,
+            logging::get_log_level,
+            logging::set_log_level,
+            logging::tail_launcher_log,
+            oauth::start_oauth_loopback_login,
+            oauth::cancel_oauth_loopback_login,
+            session::get_session_info,
+            session::logout,
+            session::get_stop_tunnels_on_logout,
+            session::set_stop_tunnels_on_logout,
+            session::security_login,
+            profiles::list_profiles,
+            profiles::add_profile,
+            profiles::rename_profile,
+            profiles::remove_profile,
+            profiles::switch_profile,
+            profiles::save_profile_tunnels,
+            profiles::get_profile_tunnels,
+            profiles::get_tunnel_settings,
+            profiles::set_tunnel_settings,
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1698,7 +1789,7 @@ fn main() {
                     if is_autostart {
                         let _ = window.eval("window.location.search += window.location.search ? '&autostart=true' : '?autostart=true'");
                         let _ = window.eval("localStorage.setItem('appStartedByAutostart', 'true')");
-                        println!("检测到自启动");
+                        log::info!("检测到自启动");
                         // window.hide().unwrap(); // 如需隐藏窗口可取消注释
                     }
                     // 检查 frpc 是否存在
@@ -1712,10 +1803,23 @@ fn main() {
                         let _ = window.emit("redirect_to_settings", "need_download");
                     }
                 }
+                if let Some(crashes) = app_handle.try_state::<crash::CrashState>() {
+                    crash::notify_previous_crashes(app_handle, &crashes);
+                }
             }
             tauri::RunEvent::Resumed => {}
             tauri::RunEvent::MainEventsCleared => {}
-            tauri::RunEvent::Exit => {}
+            tauri::RunEvent::Exit => {
+                // 保留运行的 frpc 已读取过配置，退出前删除其中含有用户密钥的配置文件
+                if let Some(processes) = app_handle.try_state::<FrpcProcesses>() {
+                    if let Ok(map) = processes.0.lock() {
+                        for process_info in map.values() {
+                            tunnel_config::remove_tunnel_config(&process_info.config_path);
+                        }
+                    }
+                }
+                update_schedule::install_on_exit(app_handle);
+            }
             tauri::RunEvent::ExitRequested { .. } => {}
             _ => {}
         }
@@ -0,0 +1,363 @@
+use crate::api_proxy::call_api;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+// Minecraft Java 版“对局域网开放”的多播地址
+const LAN_MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 2, 60);
+const LAN_PORT: u16 = 4445;
+// 游戏每 1.5 秒广播一次，超过此时间未收到视为已关闭
+const WORLD_EXPIRY: Duration = Duration::from_secs(10);
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanWorld {
+    pub motd: String,
+    pub port: u16,
+    // 广播来源地址
+    pub host: String,
+    // 端口在本机监听，即本机开放的世界
+    pub local: bool,
+    pub detected_at: u64,
+}
+
+// 自动更新隧道本地端口的结果
+#[derive(Debug, Clone, Serialize)]
+struct TunnelUpdateEvent {
+    tunnel_id: String,
+    port: u16,
+    success: bool,
+    restarted: bool,
+    message: String,
+}
+
+struct TrackedWorld {
+    world: LanWorld,
+    last_seen: Instant,
+}
+
+#[derive(Default)]
+struct LanListener {
+    cancel: Option<oneshot::Sender<()>>,
+    // 检测到本机世界后自动更新本地端口的隧道
+    target_tunnel: Option<String>,
+    worlds: HashMap<(IpAddr, u16), TrackedWorld>,
+}
+
+#[derive(Default)]
+pub struct MinecraftLanState(Mutex<LanListener>);
+
+// 解析广播内容 [MOTD]...[/MOTD][AD]端口[/AD]
+fn parse_announcement(data: &[u8]) -> Option<(String, u16)> {
+    let text = String::from_utf8_lossy(data);
+    let between = |start: &str, end: &str| -> Option<String> {
+        let from = text.find(start)? + start.len();
+        let to = from + text[from..].find(end)?;
+        Some(text[from..to].to_string())
+    };
+    let motd = between("[MOTD]", "[/MOTD]")?;
+    let port = between("[AD]", "[/AD]")?.trim().parse::<u16>().ok()?;
+    Some((motd, port))
+}
+
+// 绑定 4445 端口并加入多播组，允许与游戏客户端等共享端口
+fn bind_multicast() -> Result<UdpSocket, String> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket =
+        Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).map_err(|e| e.to_string())?;
+    socket.set_reuse_address(true).map_err(|e| e.to_string())?;
+    socket
+        .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, LAN_PORT)).into())
+        .map_err(|e| format!("绑定端口 {} 失败: {}", LAN_PORT, e))?;
+    socket
+        .join_multicast_v4(&LAN_MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| format!("加入多播组失败: {}", e))?;
+    socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+    UdpSocket::from_std(socket.into()).map_err(|e| e.to_string())
+}
+
+async fn is_listening_locally(port: u16) -> bool {
+    tokio::task::spawn_blocking(crate::local_ports::listening_endpoints)
+        .await
+        .ok()
+        .and_then(|r| r.ok())
+        .map_or(false, |endpoints| {
+            endpoints
+                .iter()
+                .any(|(protocol, _, p)| *protocol == "tcp" && *p == port)
+        })
+}
+
+// 通过 editProxy 修改隧道的本地端口，返回是否实际做了修改
+async fn update_local_port(
+    authorization: &str,
+    tunnel_id: &str,
+    port: u16,
+) -> Result<bool, String> {
+    let proxies = call_api(authorization, "getUserProxies", serde_json::json!({})).await?;
+    let proxy = proxies["data"]["list"]
+        .as_array()
+        .and_then(|list| {
+            list.iter()
+                .find(|p| p["id"].to_string().trim_matches('"') == tunnel_id)
+        })
+        .ok_or_else(|| format!("未找到隧道 #{}", tunnel_id))?;
+    if proxy["localPort"].as_u64() == Some(port as u64) {
+        return Ok(false);
+    }
+
+    // 字段对应前端编辑隧道时提交的内容，只修改本地端口；
+    // custom 与 autoTls 仅部分隧道返回，缺省值与编辑页面一致
+    let body = serde_json::json!({
+        "proxy_id": proxy["id"],
+        "name": proxy["proxyName"],
+        "type": proxy["proxyType"],
+        "node_id": proxy["nid"],
+        "local_addr": proxy["localIp"].as_str().unwrap_or("127.0.0.1"),
+        "local_port": port,
+        "remote_port": proxy["remotePort"],
+        "domain_bind": proxy["domain"],
+        "dataGzip": proxy["useCompression"],
+        "dataEncrypt": proxy["useEncryption"],
+        "custom": proxy["custom"].as_str().unwrap_or(""),
+        "autoTls": match &proxy["autoTls"] {
+            serde_json::Value::Null => serde_json::json!("false"),
+            value => value.clone(),
+        },
+        "forceHttps": proxy["forceHttps"],
+        "proxyProtocolVersion": proxy["proxyProtocolVersion"],
+    });
+    call_api(authorization, "editProxy", body).await?;
+    Ok(true)
+}
+
+// 运行中的隧道需要重启才能使用新的本地端口
+async fn restart_tunnel<R: Runtime>(
+    app: &AppHandle<R>,
+    tunnel_id: &str,
+    port: u16,
+) -> Result<bool, String> {
+    let processes = app.state::<crate::FrpcProcesses>();
+    let running = processes.0.lock().ok().and_then(|map| {
+        map.iter()
+            .find(|(_, info)| info.tunnel_id == tunnel_id)
+            .map(|(id, info)| (id.clone(), info.profile_id.clone()))
+    });
+    let (id, profile_id) = match running {
+        Some(running) => running,
+        None => return Ok(false),
+    };
+    // 健康检查仍指向旧端口，重启后以相同配置检测新端口
+    let health = crate::health::watched_config(app, &id);
+    crate::health::unwatch(app, &id);
+    crate::stop_process(&processes, &id);
+    crate::launch_tunnel(
+        app,
+        &processes,
+        id.clone(),
+        tunnel_id.to_string(),
+        profile_id,
+    )
+    .await?;
+    if let Some(mut config) = health {
+        config.local_port = port;
+        crate::health::watch(app, id, config, None);
+    }
+    Ok(true)
+}
+
+async fn apply_to_tunnel<R: Runtime>(app: AppHandle<R>, tunnel_id: String, port: u16) {
+    let result = match crate::session::current_authorization(&app) {
+        Some(authorization) => update_local_port(&authorization, &tunnel_id, port).await,
+        None => Err("未登录".to_string()),
+    };
+    let result = match result {
+        Ok(true) => restart_tunnel(&app, &tunnel_id, port)
+            .await
+            .map(|restarted| (true, restarted)),
+        Ok(false) => Ok((false, false)),
+        Err(e) => Err(e),
+    };
+    let event = match result {
+        Ok((changed, restarted)) => TunnelUpdateEvent {
+            tunnel_id: tunnel_id.clone(),
+            port,
+            success: true,
+            restarted,
+            message: if changed {
+                format!("隧道 #{} 本地端口已更新为 {}", tunnel_id, port)
+            } else {
+                format!("隧道 #{} 本地端口已是 {}", tunnel_id, port)
+            },
+        },
+        Err(e) => TunnelUpdateEvent {
+            tunnel_id: tunnel_id.clone(),
+            port,
+            success: false,
+            restarted: false,
+            message: format!("更新隧道 #{} 失败: {}", tunnel_id, e),
+        },
+    };
+    if event.success {
+        log::info!("{}", event.message);
+    } else {
+        log::warn!("{}", event.message);
+    }
+    let _ = app.emit("minecraft-lan-tunnel-updated", &event);
+}
+
+async fn handle_announcement<R: Runtime>(app: &AppHandle<R>, source: SocketAddr, data: &[u8]) {
+    let (motd, port) = match parse_announcement(data) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let key = (source.ip(), port);
+    let state = app.state::<MinecraftLanState>();
+    {
+        let mut listener = match state.0.lock() {
+            Ok(listener) => listener,
+            Err(_) => return,
+        };
+        if let Some(tracked) = listener.worlds.get_mut(&key) {
+            tracked.last_seen = Instant::now();
+            if tracked.world.motd == motd {
+                return;
+            }
+        }
+    }
+
+    let world = LanWorld {
+        motd,
+        port,
+        host: source.ip().to_string(),
+        local: is_listening_locally(port).await,
+        detected_at: crate::session::now_secs(),
+    };
+    log::info!(
+        "检测到 Minecraft 局域网世界 \"{}\" {}:{}",
+        world.motd,
+        world.host,
+        world.port
+    );
+    let _ = app.emit("minecraft-lan-detected", &world);
+
+    let target = match state.0.lock() {
+        Ok(mut listener) => {
+            listener.worlds.insert(
+                key,
+                TrackedWorld {
+                    world: world.clone(),
+                    last_seen: Instant::now(),
+                },
+            );
+            listener.target_tunnel.clone()
+        }
+        Err(_) => None,
+    };
+    if let (Some(tunnel_id), true) = (target, world.local) {
+        tauri::async_runtime::spawn(apply_to_tunnel(app.clone(), tunnel_id, port));
+    }
+}
+
+fn expire_worlds<R: Runtime>(app: &AppHandle<R>) {
+    let expired: Vec<LanWorld> = match app.state::<MinecraftLanState>().0.lock() {
+        Ok(mut listener) => {
+            let expired = listener
+                .worlds
+                .iter()
+                .filter(|(_, tracked)| tracked.last_seen.elapsed() > WORLD_EXPIRY)
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+            expired
+                .iter()
+                .filter_map(|key| listener.worlds.remove(key).map(|t| t.world))
+                .collect()
+        }
+        Err(_) => return,
+    };
+    for world in expired {
+        log::debug!("Minecraft 局域网世界已关闭: {}:{}", world.host, world.port);
+        let _ = app.emit("minecraft-lan-lost", &world);
+    }
+}
+
+async fn run<R: Runtime>(
+    app: AppHandle<R>,
+    socket: UdpSocket,
+    mut cancel_rx: oneshot::Receiver<()>,
+) {
+    let mut buf = [0u8; 1024];
+    let mut expiry = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => match received {
+                Ok((len, source)) => handle_announcement(&app, source, &buf[..len]).await,
+                Err(e) => log::debug!("接收局域网广播失败: {}", e),
+            },
+            _ = expiry.tick() => expire_worlds(&app),
+            _ = &mut cancel_rx => break,
+        }
+    }
+    log::info!("Minecraft 局域网监听已停止");
+}
+
+// 开始监听局域网世界广播，tunnel_id 为检测到本机世界时自动更新的隧道
+#[command]
+pub fn start_minecraft_lan_listener<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, MinecraftLanState>,
+    tunnel_id: Option<String>,
+) -> Result<(), String> {
+    let mut listener = state.0.lock().map_err(|e| e.to_string())?;
+    listener.target_tunnel = tunnel_id;
+    if listener.cancel.is_some() {
+        return Ok(());
+    }
+
+    let socket = bind_multicast()?;
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    listener.cancel = Some(cancel_tx);
+    tauri::async_runtime::spawn(run(app, socket, cancel_rx));
+    log::info!("Minecraft 局域网监听已启动");
+    Ok(())
+}
+
+#[command]
+pub fn stop_minecraft_lan_listener(state: State<'_, MinecraftLanState>) {
+    if let Ok(mut listener) = state.0.lock() {
+        if let Some(cancel) = listener.cancel.take() {
+            let _ = cancel.send(());
+        }
+        listener.worlds.clear();
+    }
+}
+
+// 修改自动更新的隧道，None 表示只检测不修改
+#[command]
+pub fn set_minecraft_lan_tunnel(state: State<'_, MinecraftLanState>, tunnel_id: Option<String>) {
+    if let Ok(mut listener) = state.0.lock() {
+        listener.target_tunnel = tunnel_id;
+    }
+}
+
+// 当前检测到的局域网世界
+#[command]
+pub fn get_minecraft_lan_worlds(state: State<'_, MinecraftLanState>) -> Vec<LanWorld> {
+    state
+        .0
+        .lock()
+        .map(|listener| {
+            listener
+                .worlds
+                .values()
+                .map(|tracked| tracked.world.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
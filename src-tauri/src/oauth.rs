@@ -0,0 +1,327 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle, Runtime, State};
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+
+// 通过第三方页面中转 code 的默认回调地址
+pub const DEFAULT_REDIRECT_URL: &str = "https://www.zyghit.cn/ofcpl_login";
+
+// 本地回调服务默认等待时间
+const DEFAULT_LOGIN_TIMEOUT_SECS: u64 = 300;
+// 本地回调路径
+const CALLBACK_PATH: &str = "/callback";
+// 单个回调请求的最大长度
+const MAX_REQUEST_SIZE: usize = 8192;
+// 读取单个回调请求的超时
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize)]
+pub struct OAuthResponse {
+    pub authorization: String,
+    pub flag: bool,
+    pub msg: String,
+    pub data: String,
+}
+
+// 正在进行的本地回调登录，保存取消信号
+#[derive(Default)]
+pub struct OAuthLoopbackState(Mutex<Option<oneshot::Sender<()>>>);
+
+// 使用 code 向 OpenFrp 换取会话，redirect_url 必须与授权时使用的一致
+pub async fn exchange_code(code: String, redirect_url: &str) -> Result<OAuthResponse, String> {
+    let client = reqwest::Client::new();
+    let mut form = std::collections::HashMap::new();
+    form.insert("code", code);
+    form.insert("redirect_url", redirect_url.to_string());
+
+    let res = client
+        .post("https://api.openfrp.net/oauth2/callback")
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let headers = res.headers();
+    let auth = headers
+        .get("authorization")
+        .ok_or("登录失败: 未能找到 Authorization")?
+        .to_str()
+        .map_err(|e| e.to_string())?
+        .to_string();
+
+    let json = res
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(OAuthResponse {
+        authorization: auth,
+        flag: json["flag"].as_bool().unwrap_or(false),
+        msg: json["msg"].as_str().unwrap_or("").to_string(),
+        data: json["data"].as_str().unwrap_or("").to_string(),
+    })
+}
+
+// 向 OpenFrp 获取 Natayark 授权页地址，并附加随机 state
+async fn get_authorize_url(redirect_url: &str, oauth_state: &str) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let json = client
+        .get("https://api.openfrp.net/oauth2/login")
+        .query(&[("redirect_url", redirect_url)])
+        .send()
+        .await
+        .map_err(|e| format!("获取登录地址失败: {}", e))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("解析登录地址失败: {}", e))?;
+
+    if !json["flag"].as_bool().unwrap_or(false) {
+        return Err(format!(
+            "获取登录地址失败: {}",
+            json["msg"].as_str().unwrap_or("未知错误")
+        ));
+    }
+
+    let url = json["data"]
+        .as_str()
+        .ok_or("获取登录地址失败: 返回内容为空")?;
+    let mut url = reqwest::Url::parse(url).map_err(|e| format!("登录地址格式错误: {}", e))?;
+
+    // 替换掉服务端可能带上的 state，使用本次登录生成的值
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "state")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("state", oauth_state);
+
+    // code 由 OpenFrp 服务端通过 /oauth2/callback 兑换，该接口不接受 code_verifier，
+    // 因此无法在客户端启用 PKCE，这里仅依靠 state 防止伪造回调
+    Ok(url.to_string())
+}
+
+fn random_state() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+// 逐字节比较，避免提前返回泄露匹配长度
+fn state_matches(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, body: &str) {
+    let html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>OpenFrp 跨平台启动器</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center;padding-top:80px\"><h2>{}</h2></body></html>",
+        body
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        html.len(),
+        html
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// 读取请求行，返回请求目标（路径 + 查询参数）
+async fn read_request_target(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.windows(2).any(|w| w == b"\r\n") || buf.len() >= MAX_REQUEST_SIZE {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&buf);
+    let line = request.lines().next()?;
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_string()),
+        _ => None,
+    }
+}
+
+// 处理一个回调连接：返回 None 表示不是有效的回调，继续等待
+async fn handle_callback(
+    mut stream: TcpStream,
+    oauth_state: Arc<str>,
+) -> Option<Result<String, String>> {
+    // 浏览器预连接等空闲连接不会发送数据，超时后直接关闭
+    let target =
+        match tokio::time::timeout(REQUEST_READ_TIMEOUT, read_request_target(&mut stream)).await {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                write_response(&mut stream, "400 Bad Request", "无效的请求").await;
+                return None;
+            }
+            Err(_) => return None,
+        };
+
+    let url = match reqwest::Url::parse(&format!("http://127.0.0.1{}", target)) {
+        Ok(url) if url.path() == CALLBACK_PATH => url,
+        // 浏览器可能顺带请求 favicon 等资源，忽略并继续等待
+        _ => {
+            write_response(&mut stream, "404 Not Found", "未找到").await;
+            return None;
+        }
+    };
+
+    let mut code = None;
+    let mut returned_state = None;
+    let mut error = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => returned_state = Some(value.into_owned()),
+            "error" => error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    if !state_matches(&oauth_state, returned_state.as_deref().unwrap_or("")) {
+        // state 不匹配的请求可能是伪造的回调，拒绝后继续等待真正的回调
+        write_response(
+            &mut stream,
+            "400 Bad Request",
+            "登录校验失败，请返回启动器重试",
+        )
+        .await;
+        return None;
+    }
+
+    if let Some(error) = error {
+        write_response(&mut stream, "200 OK", "授权已取消，可以关闭此页面").await;
+        return Some(Err(format!("授权失败: {}", error)));
+    }
+
+    match code.filter(|c| !c.is_empty()) {
+        Some(code) => {
+            write_response(
+                &mut stream,
+                "200 OK",
+                "登录成功，可以关闭此页面并返回启动器",
+            )
+            .await;
+            Some(Ok(code))
+        }
+        None => {
+            write_response(&mut stream, "400 Bad Request", "回调缺少 code").await;
+            Some(Err("登录失败: 回调缺少 code".to_string()))
+        }
+    }
+}
+
+// 等待浏览器回调，校验 state 后返回 code；每个连接单独处理，空闲连接不会阻塞后续回调
+async fn wait_for_code(listener: TcpListener, oauth_state: &str) -> Result<String, String> {
+    let oauth_state: Arc<str> = Arc::from(oauth_state);
+    // 返回时未完成的连接随 JoinSet 一起取消
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted.map_err(|e| format!("本地回调服务异常: {}", e))?;
+                connections.spawn(handle_callback(stream, oauth_state.clone()));
+            }
+            Some(joined) = connections.join_next() => {
+                if let Ok(Some(result)) = joined {
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+// 启动本地回调登录：监听 127.0.0.1 随机端口，打开授权页并等待回调，完成后直接兑换会话
+#[command]
+pub async fn start_oauth_loopback_login<R: Runtime>(
+    app: AppHandle<R>,
+    loopback: State<'_, OAuthLoopbackState>,
+    timeout_secs: Option<u64>,
+) -> Result<OAuthResponse, String> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("启动本地回调服务失败: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("启动本地回调服务失败: {}", e))?
+        .port();
+    let redirect_url = format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH);
+    let oauth_state = random_state();
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    if let Some(previous) = loopback.0.lock().unwrap().replace(cancel_tx) {
+        // 同一时间只允许一个登录流程，取消上一个
+        let _ = previous.send(());
+    }
+
+    let result: Result<String, String> = async {
+        let authorize_url = get_authorize_url(&redirect_url, &oauth_state).await?;
+        app.opener()
+            .open_url(authorize_url, None::<&str>)
+            .map_err(|e| format!("打开浏览器失败: {}", e))?;
+        log::info!("等待 OAuth 回调，端口: {}", port);
+
+        let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_LOGIN_TIMEOUT_SECS));
+        tokio::select! {
+            result = tokio::time::timeout(timeout, wait_for_code(listener, &oauth_state)) => {
+                result.unwrap_or_else(|_| Err("登录超时，请重试".to_string()))
+            }
+            _ = cancel_rx => Err("登录已取消".to_string()),
+        }
+    }
+    .await;
+
+    // 流程结束（包括获取登录地址或打开浏览器失败）后清理取消信号，
+    // 若已被新的流程替换，则保留新流程的发送端
+    {
+        let mut sender = loopback.0.lock().unwrap();
+        if sender.as_ref().map_or(false, |s| s.is_closed()) {
+            sender.take();
+        }
+    }
+
+    let code = result?;
+    let response = exchange_code(code, &redirect_url).await?;
+    if response.flag {
+        crate::session::store_session(&app, crate::session::Session::from_oauth(&response))?;
+    }
+    Ok(response)
+}
+
+// 取消正在进行的本地回调登录
+#[command]
+pub fn cancel_oauth_loopback_login(loopback: State<'_, OAuthLoopbackState>) -> Result<(), String> {
+    match loopback.0.lock().unwrap().take() {
+        Some(sender) if !sender.is_closed() => {
+            let _ = sender.send(());
+            Ok(())
+        }
+        _ => Err("没有正在进行的登录".to_string()),
+    }
+}
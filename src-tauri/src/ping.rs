@@ -0,0 +1,528 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Runtime, State};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+
+// 默认探测次数、间隔与单次超时
+const DEFAULT_COUNT: u32 = 4;
+const DEFAULT_INTERVAL_MS: u64 = 200;
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+// 参数上限，避免前端传入过大的值长时间占用
+const MAX_COUNT: u32 = 100;
+const MIN_INTERVAL_MS: u64 = 100;
+const MAX_INTERVAL_MS: u64 = 10_000;
+const MAX_TIMEOUT_MS: u64 = 30_000;
+// 节点测速默认并发数与结果缓存时间
+const DEFAULT_NODE_CONCURRENCY: usize = 16;
+const MAX_NODE_CONCURRENCY: usize = 64;
+const NODE_RANK_CACHE_TTL: Duration = Duration::from_secs(300);
+
+// 单次探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PingAttempt {
+    pub seq: u32,
+    pub success: bool,
+    pub latency_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+// 多次探测的统计结果，丢包的探测不计入延迟统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PingStats {
+    pub sent: u32,
+    pub received: u32,
+    pub loss_percent: f64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub median_ms: Option<f64>,
+    pub stddev_ms: Option<f64>,
+}
+
+impl PingStats {
+    pub fn from_attempts(attempts: &[PingAttempt]) -> Self {
+        let mut samples: Vec<f64> = attempts.iter().filter_map(|a| a.latency_ms).collect();
+        let sent = attempts.len() as u32;
+        let received = samples.len() as u32;
+        let loss_percent = if sent == 0 {
+            0.0
+        } else {
+            (sent - received) as f64 * 100.0 / sent as f64
+        };
+        if samples.is_empty() {
+            return PingStats {
+                sent,
+                received,
+                loss_percent,
+                ..Default::default()
+            };
+        }
+
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let n = samples.len() as f64;
+        let avg = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s - avg).powi(2)).sum::<f64>() / n;
+        let mid = samples.len() / 2;
+        let median = if samples.len() % 2 == 0 {
+            (samples[mid - 1] + samples[mid]) / 2.0
+        } else {
+            samples[mid]
+        };
+
+        PingStats {
+            sent,
+            received,
+            loss_percent,
+            min_ms: samples.first().copied(),
+            avg_ms: Some(avg),
+            max_ms: samples.last().copied(),
+            median_ms: Some(median),
+            stddev_ms: Some(variance.sqrt()),
+        }
+    }
+}
+
+// tcp_ping 的返回值，保留 success / latency_ms / message 以兼容旧版前端
+#[derive(Debug, Clone, Serialize)]
+pub struct PingReport {
+    pub success: bool,
+    pub latency_ms: Option<f64>,
+    pub message: String,
+    // 表现最好的地址
+    pub address: Option<String>,
+    // DNS 解析耗时，与连接耗时分开统计
+    pub dns_ms: Option<f64>,
+    #[serde(flatten)]
+    pub stats: PingStats,
+    pub attempts: Vec<PingAttempt>,
+    // 按地址族分组的每个地址结果
+    pub ipv4: Vec<AddressReport>,
+    pub ipv6: Vec<AddressReport>,
+}
+
+// 探测参数
+#[derive(Debug, Clone, Copy)]
+pub struct PingOptions {
+    pub count: u32,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl PingOptions {
+    pub fn new(count: Option<u32>, interval_ms: Option<u64>, timeout_ms: Option<u64>) -> Self {
+        PingOptions {
+            count: count.unwrap_or(DEFAULT_COUNT).clamp(1, MAX_COUNT),
+            interval: Duration::from_millis(
+                interval_ms
+                    .unwrap_or(DEFAULT_INTERVAL_MS)
+                    .clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+            ),
+            timeout: Duration::from_millis(
+                timeout_ms
+                    .unwrap_or(DEFAULT_TIMEOUT_MS)
+                    .clamp(1, MAX_TIMEOUT_MS),
+            ),
+        }
+    }
+}
+
+// 单次 TCP 连接，返回连接耗时（毫秒）
+pub async fn tcp_connect_once(addr: SocketAddr, timeout: Duration) -> Result<f64, String> {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => Ok(start.elapsed().as_secs_f64() * 1000.0),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("连接超时（{}ms）", timeout.as_millis())),
+    }
+}
+
+// 对同一地址按间隔连续探测
+pub async fn probe_tcp(addr: SocketAddr, options: PingOptions) -> Vec<PingAttempt> {
+    let mut attempts = Vec::with_capacity(options.count as usize);
+    for seq in 0..options.count {
+        if seq > 0 && !options.interval.is_zero() {
+            tokio::time::sleep(options.interval).await;
+        }
+        let attempt = match tcp_connect_once(addr, options.timeout).await {
+            Ok(latency) => PingAttempt {
+                seq,
+                success: true,
+                latency_ms: Some(latency),
+                error: None,
+            },
+            Err(e) => PingAttempt {
+                seq,
+                success: false,
+                latency_ms: None,
+                error: Some(e),
+            },
+        };
+        attempts.push(attempt);
+    }
+    attempts
+}
+
+// 地址族选择
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    fn matches(self, addr: &SocketAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => addr.is_ipv4(),
+            AddressFamily::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+// 解析主机名，返回去重后的地址与 DNS 解析耗时（毫秒）
+pub async fn resolve(
+    host: &str,
+    port: u16,
+    family: AddressFamily,
+) -> Result<(Vec<SocketAddr>, f64), String> {
+    let start = Instant::now();
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("地址解析失败: {}", e))?;
+    let dns_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let mut result: Vec<SocketAddr> = Vec::new();
+    for addr in addrs.filter(|addr| family.matches(addr)) {
+        if !result.contains(&addr) {
+            result.push(addr);
+        }
+    }
+    if result.is_empty() {
+        return Err(match family {
+            AddressFamily::Any => "未能解析到有效的地址".to_string(),
+            AddressFamily::Ipv4 => "未能解析到 IPv4 地址".to_string(),
+            AddressFamily::Ipv6 => "未能解析到 IPv6 地址".to_string(),
+        });
+    }
+    Ok((result, dns_ms))
+}
+
+// 单个地址的探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressReport {
+    pub address: String,
+    #[serde(flatten)]
+    pub stats: PingStats,
+    pub attempts: Vec<PingAttempt>,
+}
+
+// 延迟更低、丢包更少的地址排在前面
+fn better(a: &PingStats, b: &PingStats) -> std::cmp::Ordering {
+    a.loss_percent
+        .partial_cmp(&b.loss_percent)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then(
+            a.median_ms
+                .unwrap_or(f64::MAX)
+                .partial_cmp(&b.median_ms.unwrap_or(f64::MAX))
+                .unwrap_or(std::cmp::Ordering::Equal),
+        )
+}
+
+fn failed_report(message: String, dns_ms: Option<f64>) -> PingReport {
+    PingReport {
+        success: false,
+        latency_ms: None,
+        message,
+        address: None,
+        dns_ms,
+        stats: PingStats::default(),
+        attempts: Vec::new(),
+        ipv4: Vec::new(),
+        ipv6: Vec::new(),
+    }
+}
+
+// 对解析到的所有 IPv4 / IPv6 地址同时探测，顶层统计取表现最好的地址
+#[command]
+pub async fn tcp_ping(
+    host: String,
+    port: u16,
+    count: Option<u32>,
+    interval_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+    family: Option<AddressFamily>,
+) -> Result<PingReport, String> {
+    let options = PingOptions::new(count, interval_ms, timeout_ms);
+    log::debug!(
+        "tcp_ping {}:{}，次数 {}，超时 {}ms",
+        host,
+        port,
+        options.count,
+        options.timeout.as_millis()
+    );
+
+    let (addrs, dns_ms) = match resolve(&host, port, family.unwrap_or_default()).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Ok(failed_report(e, None)),
+    };
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for addr in addrs {
+        tasks.spawn(async move {
+            let attempts = probe_tcp(addr, options).await;
+            (addr, attempts)
+        });
+    }
+
+    let mut ipv4 = Vec::new();
+    let mut ipv6 = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        let (addr, attempts) = result.map_err(|e| e.to_string())?;
+        let report = AddressReport {
+            address: addr.to_string(),
+            stats: PingStats::from_attempts(&attempts),
+            attempts,
+        };
+        if addr.is_ipv4() {
+            ipv4.push(report);
+        } else {
+            ipv6.push(report);
+        }
+    }
+    ipv4.sort_by(|a, b| better(&a.stats, &b.stats));
+    ipv6.sort_by(|a, b| better(&a.stats, &b.stats));
+
+    let best = ipv4
+        .iter()
+        .chain(ipv6.iter())
+        .min_by(|a, b| better(&a.stats, &b.stats))
+        .cloned();
+    let best = match best {
+        Some(best) => best,
+        None => {
+            return Ok(failed_report(
+                "未能解析到有效的地址".to_string(),
+                Some(dns_ms),
+            ))
+        }
+    };
+
+    let message = match (best.stats.avg_ms, best.attempts.last()) {
+        (Some(avg), _) => format!(
+            "测试连接成功，平均延迟 {:.0}ms，丢包率 {:.0}%",
+            avg, best.stats.loss_percent
+        ),
+        (None, Some(PingAttempt { error: Some(e), .. })) => {
+            format!("发生错误，连接失败: {}", e)
+        }
+        _ => "连接失败".to_string(),
+    };
+
+    Ok(PingReport {
+        success: best.stats.received > 0,
+        latency_ms: best.stats.avg_ms,
+        message,
+        address: Some(best.address),
+        dns_ms: Some(dns_ms),
+        stats: best.stats,
+        attempts: best.attempts,
+        ipv4,
+        ipv6,
+    })
+}
+
+// 待测速的节点，字段与 getNodeList 返回的节点一致，其余字段忽略
+// 无权查看的节点 hostname 与 port 均为提示文本，此时 port 记为 0
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct NodeTarget {
+    pub id: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default, deserialize_with = "port_or_text")]
+    pub port: u16,
+}
+
+impl NodeTarget {
+    // 是否有可用于测速的地址
+    fn is_usable(&self) -> bool {
+        self.port != 0
+            && !self.hostname.is_empty()
+            && self
+                .hostname
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+    }
+}
+
+// port 可能是数字、数字字符串、提示文本或 null，无法解析时返回 0
+fn port_or_text<'de, D>(deserializer: D) -> Result<u16, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PortValue {
+        Number(u64),
+        Text(String),
+    }
+    Ok(match Option::<PortValue>::deserialize(deserializer)? {
+        Some(PortValue::Number(port)) => u16::try_from(port).unwrap_or(0),
+        Some(PortValue::Text(text)) => text.trim().parse().unwrap_or(0),
+        None => 0,
+    })
+}
+
+// 单个节点的测速结果
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeRanking {
+    #[serde(flatten)]
+    pub node: NodeTarget,
+    pub address: Option<String>,
+    pub reachable: bool,
+    #[serde(flatten)]
+    pub stats: PingStats,
+    pub error: Option<String>,
+}
+
+// 节点测速进度事件
+#[derive(Debug, Clone, Serialize)]
+struct NodePingProgress<'a> {
+    done: usize,
+    total: usize,
+    result: &'a NodeRanking,
+}
+
+struct NodeRankCacheEntry {
+    created: Instant,
+    nodes: Vec<NodeTarget>,
+    rankings: Vec<NodeRanking>,
+}
+
+// 最近一次节点测速结果，几分钟内重复请求直接返回
+#[derive(Default)]
+pub struct NodeRankCache(Mutex<Option<NodeRankCacheEntry>>);
+
+async fn rank_node(node: NodeTarget, options: PingOptions) -> NodeRanking {
+    let addr = match resolve(&node.hostname, node.port, AddressFamily::Any).await {
+        Ok((addrs, _)) => addrs[0],
+        Err(e) => {
+            return NodeRanking {
+                node,
+                address: None,
+                reachable: false,
+                stats: PingStats::default(),
+                error: Some(e),
+            }
+        }
+    };
+
+    let attempts = probe_tcp(addr, options).await;
+    let stats = PingStats::from_attempts(&attempts);
+    let error = if stats.received == 0 {
+        attempts.iter().rev().find_map(|a| a.error.clone())
+    } else {
+        None
+    };
+    NodeRanking {
+        node,
+        address: Some(addr.to_string()),
+        reachable: stats.received > 0,
+        stats,
+        error,
+    }
+}
+
+// 排序：可达节点优先，其次丢包率低，最后中位延迟低
+fn sort_rankings(rankings: &mut [NodeRanking]) {
+    rankings.sort_by(|a, b| {
+        b.reachable
+            .cmp(&a.reachable)
+            .then(
+                a.stats
+                    .loss_percent
+                    .partial_cmp(&b.stats.loss_percent)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+            .then(
+                a.stats
+                    .median_ms
+                    .unwrap_or(f64::MAX)
+                    .partial_cmp(&b.stats.median_ms.unwrap_or(f64::MAX))
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+    });
+}
+
+// 并发测试所有节点并按延迟与丢包排序，每完成一个节点发送 node-ping-progress 事件
+#[command]
+pub async fn rank_nodes<R: Runtime>(
+    app: AppHandle<R>,
+    cache: State<'_, NodeRankCache>,
+    nodes: Vec<NodeTarget>,
+    count: Option<u32>,
+    timeout_ms: Option<u64>,
+    concurrency: Option<usize>,
+    force: Option<bool>,
+) -> Result<Vec<NodeRanking>, String> {
+    // 跳过无权查看地址的节点，不影响其余节点测速
+    let nodes: Vec<NodeTarget> = nodes.into_iter().filter(NodeTarget::is_usable).collect();
+
+    if !force.unwrap_or(false) {
+        if let Some(entry) = cache.0.lock().unwrap().as_ref() {
+            if entry.created.elapsed() < NODE_RANK_CACHE_TTL && entry.nodes == nodes {
+                return Ok(entry.rankings.clone());
+            }
+        }
+    }
+
+    let options = PingOptions::new(count, Some(DEFAULT_INTERVAL_MS), timeout_ms);
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_NODE_CONCURRENCY)
+        .clamp(1, MAX_NODE_CONCURRENCY);
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let total = nodes.len();
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for node in nodes.iter().cloned() {
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            rank_node(node, options).await
+        });
+    }
+
+    let mut rankings = Vec::with_capacity(total);
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(ranking) => {
+                let _ = app.emit(
+                    "node-ping-progress",
+                    NodePingProgress {
+                        done: rankings.len() + 1,
+                        total,
+                        result: &ranking,
+                    },
+                );
+                rankings.push(ranking);
+            }
+            Err(e) => log::warn!("节点测速任务异常: {}", e),
+        }
+    }
+
+    sort_rankings(&mut rankings);
+    *cache.0.lock().unwrap() = Some(NodeRankCacheEntry {
+        created: Instant::now(),
+        nodes,
+        rankings: rankings.clone(),
+    });
+    Ok(rankings)
+}
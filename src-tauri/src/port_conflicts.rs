@@ -0,0 +1,384 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use tauri::{command, AppHandle, Manager, Runtime};
+
+use crate::local_ports::{self, LocalSocket};
+
+// 需要检查的隧道，visitor 的绑定地址未给出时从自定义配置中读取
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelPorts {
+    pub id: String,
+    pub name: Option<String>,
+    pub proxy_type: String,
+    pub local_ip: Option<String>,
+    pub local_port: Option<u16>,
+    pub custom: Option<String>,
+    pub visitor: Option<bool>,
+    pub bind_addr: Option<String>,
+    pub bind_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    // 隧道指向的本地端口没有程序在监听
+    NoListener,
+    // 多个 visitor 绑定同一端口
+    DuplicateBind,
+    // visitor 的绑定端口已被其他进程占用
+    BindPortInUse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortConflict {
+    pub kind: ConflictKind,
+    pub tunnel_ids: Vec<String>,
+    pub protocol: &'static str,
+    pub address: String,
+    pub port: u16,
+    // 占用端口的进程
+    pub owner: Option<LocalSocket>,
+    // 占用端口的运行中隧道
+    pub owner_tunnel: Option<String>,
+    pub message: String,
+}
+
+struct Visitor<'a> {
+    tunnel: &'a TunnelPorts,
+    protocol: &'static str,
+    addr: String,
+    port: u16,
+}
+
+// 读取 frp 自定义配置中的 key = value
+fn custom_value<'a>(custom: &'a str, key: &str) -> Option<&'a str> {
+    custom.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim().trim_matches('"'))
+    })
+}
+
+// 隧道类型对应的传输协议
+fn protocol_of(proxy_type: &str) -> &'static str {
+    match proxy_type.to_lowercase().as_str() {
+        "udp" | "sudp" => "udp",
+        _ => "tcp",
+    }
+}
+
+fn as_visitor(tunnel: &TunnelPorts) -> Option<Visitor<'_>> {
+    let custom = tunnel.custom.as_deref().unwrap_or("");
+    let is_visitor = tunnel
+        .visitor
+        .unwrap_or_else(|| custom_value(custom, "role") == Some("visitor"));
+    if !is_visitor {
+        return None;
+    }
+    let port = tunnel
+        .bind_port
+        .or_else(|| custom_value(custom, "bind_port")?.parse().ok())?;
+    let addr = tunnel
+        .bind_addr
+        .clone()
+        .or_else(|| custom_value(custom, "bind_addr").map(|s| s.to_string()))
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    Some(Visitor {
+        tunnel,
+        protocol: protocol_of(&tunnel.proxy_type),
+        addr,
+        port,
+    })
+}
+
+fn parse_ip(addr: &str) -> Option<IpAddr> {
+    if addr.eq_ignore_ascii_case("localhost") {
+        return Some(IpAddr::from([127, 0, 0, 1]));
+    }
+    addr.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+// 两个地址是否会绑定到同一端口上（任一方为全部地址即冲突）
+fn addrs_overlap(a: &str, b: &str) -> bool {
+    match (parse_ip(a), parse_ip(b)) {
+        (Some(a), Some(b)) => a.is_unspecified() || b.is_unspecified() || a == b,
+        _ => a == b,
+    }
+}
+
+fn label(tunnel: &TunnelPorts) -> String {
+    match &tunnel.name {
+        Some(name) => format!("#{} {}", tunnel.id, name),
+        None => format!("#{}", tunnel.id),
+    }
+}
+
+fn owner_name(socket: &LocalSocket) -> String {
+    match (&socket.process, socket.pid) {
+        (Some(process), Some(pid)) => format!("{} (PID {})", process, pid),
+        (None, Some(pid)) => format!("PID {}", pid),
+        _ => "未知进程".to_string(),
+    }
+}
+
+// 运行中隧道的 frpc 进程号
+fn running_tunnels<R: Runtime>(app: &AppHandle<R>) -> HashMap<u32, String> {
+    app.state::<crate::FrpcProcesses>()
+        .0
+        .lock()
+        .map(|map| {
+            map.iter()
+                .map(|(id, info)| (info.child.id(), id.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 本机网卡上的地址，用于区分指向局域网其他主机的隧道
+fn interface_addrs() -> Vec<IpAddr> {
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces.iter().map(|iface| iface.ip()).collect(),
+        Err(e) => {
+            log::warn!("读取网卡地址失败: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn find_conflicts(
+    tunnels: &[TunnelPorts],
+    sockets: &[LocalSocket],
+    running: &HashMap<u32, String>,
+    local_addrs: &[IpAddr],
+) -> Vec<PortConflict> {
+    let mut conflicts = Vec::new();
+    let visitors: Vec<Visitor> = tunnels.iter().filter_map(as_visitor).collect();
+
+    // 1. 隧道目标没有监听
+    for tunnel in tunnels {
+        if visitors.iter().any(|v| v.tunnel.id == tunnel.id) {
+            continue;
+        }
+        let port = match tunnel.local_port {
+            Some(port) if port > 0 => port,
+            _ => continue,
+        };
+        let local_ip = tunnel.local_ip.as_deref().unwrap_or("127.0.0.1");
+        let is_local = parse_ip(local_ip).map_or(false, |ip| {
+            ip.is_loopback() || ip.is_unspecified() || local_addrs.contains(&ip)
+        });
+        if !is_local {
+            continue;
+        }
+        let protocol = protocol_of(&tunnel.proxy_type);
+        let listening = sockets.iter().any(|s| {
+            s.protocol == protocol && s.port == port && addrs_overlap(&s.address, local_ip)
+        });
+        if !listening {
+            conflicts.push(PortConflict {
+                kind: ConflictKind::NoListener,
+                tunnel_ids: vec![tunnel.id.clone()],
+                protocol,
+                address: local_ip.to_string(),
+                port,
+                owner: None,
+                owner_tunnel: None,
+                message: format!(
+                    "隧道 {} 指向的本地服务 {}:{} ({}) 没有程序在监听",
+                    label(tunnel),
+                    local_ip,
+                    port,
+                    protocol.to_uppercase()
+                ),
+            });
+        }
+    }
+
+    // 2. visitor 之间绑定同一端口
+    let mut reported: Vec<usize> = Vec::new();
+    for (i, a) in visitors.iter().enumerate() {
+        if reported.contains(&i) {
+            continue;
+        }
+        let group: Vec<usize> = visitors
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| {
+                b.protocol == a.protocol && b.port == a.port && addrs_overlap(&a.addr, &b.addr)
+            })
+            .map(|(j, _)| j)
+            .collect();
+        if group.len() > 1 {
+            reported.extend(&group);
+            let names: Vec<String> = group.iter().map(|j| label(visitors[*j].tunnel)).collect();
+            conflicts.push(PortConflict {
+                kind: ConflictKind::DuplicateBind,
+                tunnel_ids: group
+                    .iter()
+                    .map(|j| visitors[*j].tunnel.id.clone())
+                    .collect(),
+                protocol: a.protocol,
+                address: a.addr.clone(),
+                port: a.port,
+                owner: None,
+                owner_tunnel: None,
+                message: format!(
+                    "隧道 {} 都绑定到 {}:{}，无法同时启动",
+                    names.join("、"),
+                    a.addr,
+                    a.port
+                ),
+            });
+        }
+    }
+
+    // 3. visitor 绑定端口已被占用，隧道自身运行中时不算冲突
+    for visitor in &visitors {
+        let owner = sockets.iter().find(|s| {
+            s.protocol == visitor.protocol
+                && s.port == visitor.port
+                && addrs_overlap(&s.address, &visitor.addr)
+                && s.pid
+                    .and_then(|pid| running.get(&pid))
+                    .map_or(true, |id| *id != visitor.tunnel.id)
+        });
+        if let Some(owner) = owner {
+            let owner_tunnel = owner.pid.and_then(|pid| running.get(&pid)).cloned();
+            let by = match &owner_tunnel {
+                Some(id) => format!("运行中的隧道 #{}", id),
+                None => owner_name(owner),
+            };
+            conflicts.push(PortConflict {
+                kind: ConflictKind::BindPortInUse,
+                tunnel_ids: vec![visitor.tunnel.id.clone()],
+                protocol: visitor.protocol,
+                address: visitor.addr.clone(),
+                port: visitor.port,
+                owner: Some(owner.clone()),
+                owner_tunnel,
+                message: format!(
+                    "隧道 {} 的绑定端口 {}:{} 已被 {} 占用",
+                    label(visitor.tunnel),
+                    visitor.addr,
+                    visitor.port,
+                    by
+                ),
+            });
+        }
+    }
+
+    conflicts
+}
+
+// 启动前检查隧道列表的本地端口冲突
+#[command]
+pub async fn check_port_conflicts<R: Runtime>(
+    app: AppHandle<R>,
+    tunnels: Vec<TunnelPorts>,
+) -> Result<Vec<PortConflict>, String> {
+    let (sockets, local_addrs) =
+        tokio::task::spawn_blocking(|| (local_ports::list_sockets(true), interface_addrs()))
+            .await
+            .map_err(|e| e.to_string())?;
+    let running = running_tunnels(&app);
+    Ok(find_conflicts(&tunnels, &sockets?, &running, &local_addrs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tunnel(id: &str, proxy_type: &str) -> TunnelPorts {
+        TunnelPorts {
+            id: id.to_string(),
+            name: None,
+            proxy_type: proxy_type.to_string(),
+            local_ip: None,
+            local_port: None,
+            custom: None,
+            visitor: None,
+            bind_addr: None,
+            bind_port: None,
+        }
+    }
+
+    fn visitor(id: &str, bind_addr: &str, bind_port: u16) -> TunnelPorts {
+        TunnelPorts {
+            visitor: Some(true),
+            bind_addr: Some(bind_addr.to_string()),
+            bind_port: Some(bind_port),
+            ..tunnel(id, "stcp")
+        }
+    }
+
+    fn socket(protocol: &'static str, address: &str, port: u16, pid: u32) -> LocalSocket {
+        LocalSocket {
+            protocol,
+            address: address.to_string(),
+            port,
+            family: "ipv4",
+            state: "LISTEN".to_string(),
+            pid: Some(pid),
+            process: Some("app".to_string()),
+            exe: None,
+        }
+    }
+
+    fn kinds(conflicts: &[PortConflict]) -> Vec<ConflictKind> {
+        conflicts.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn no_listener_on_lan_address() {
+        let lan: IpAddr = "192.168.1.10".parse().unwrap();
+        let mut t = tunnel("1", "tcp");
+        t.local_ip = Some(lan.to_string());
+        t.local_port = Some(25565);
+
+        // 服务没有启动
+        let conflicts = find_conflicts(&[t.clone()], &[], &HashMap::new(), &[lan]);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::NoListener]);
+
+        // 服务监听在全部地址上
+        let sockets = [socket("tcp", "0.0.0.0", 25565, 100)];
+        assert!(find_conflicts(&[t.clone()], &sockets, &HashMap::new(), &[lan]).is_empty());
+
+        // 局域网其他主机不检查
+        assert!(find_conflicts(&[t], &[], &HashMap::new(), &[]).is_empty());
+    }
+
+    #[test]
+    fn duplicate_bind() {
+        let tunnels = [
+            visitor("1", "127.0.0.1", 6000),
+            visitor("2", "0.0.0.0", 6000),
+            visitor("3", "127.0.0.1", 6001),
+        ];
+        let conflicts = find_conflicts(&tunnels, &[], &HashMap::new(), &[]);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::DuplicateBind]);
+        assert_eq!(conflicts[0].tunnel_ids, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn bind_port_in_use() {
+        let tunnels = [visitor("1", "127.0.0.1", 6000)];
+        let sockets = [socket("tcp", "127.0.0.1", 6000, 100)];
+
+        let conflicts = find_conflicts(&tunnels, &sockets, &HashMap::new(), &[]);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::BindPortInUse]);
+        assert_eq!(conflicts[0].owner_tunnel, None);
+
+        // 被其他运行中的隧道占用
+        let running = HashMap::from([(100, "2".to_string())]);
+        let conflicts = find_conflicts(&tunnels, &sockets, &running, &[]);
+        assert_eq!(conflicts[0].owner_tunnel.as_deref(), Some("2"));
+
+        // 隧道自身正在运行
+        let running = HashMap::from([(100, "1".to_string())]);
+        assert!(find_conflicts(&tunnels, &sockets, &running, &[]).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+use tokio::sync::oneshot;
+
+use crate::local_ports::{self, LocalSocket};
+
+// 轮询间隔，空闲时每次只读取套接字表，不查询所属进程。
+// Linux 直接读取 /proc/net，其他系统每次都要运行 netstat / lsof，轮询间隔放宽
+#[cfg(target_os = "linux")]
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+#[cfg(not(target_os = "linux"))]
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+// 高于此端口的 UDP 套接字多为系统分配的临时端口，除非是已知服务否则忽略
+const EPHEMERAL_PORT_START: u16 = 32768;
+
+// 常见服务：端口、协议、名称、建议的隧道类型
+const KNOWN_SERVICES: &[(u16, &str, &str, &str)] = &[
+    (25565, "tcp", "Minecraft Java 版", "tcp"),
+    (19132, "udp", "Minecraft 基岩版", "udp"),
+    (19133, "udp", "Minecraft 基岩版", "udp"),
+    (27015, "udp", "Source 引擎游戏服务器", "udp"),
+    (7777, "tcp", "泰拉瑞亚", "tcp"),
+    (2456, "udp", "Valheim", "udp"),
+    (34197, "udp", "Factorio", "udp"),
+    (22, "tcp", "SSH", "tcp"),
+    (3389, "tcp", "远程桌面", "tcp"),
+    (5900, "tcp", "VNC", "tcp"),
+    (3306, "tcp", "MySQL", "tcp"),
+    (6379, "tcp", "Redis", "tcp"),
+    (80, "tcp", "HTTP", "http"),
+    (443, "tcp", "HTTPS", "https"),
+    (8080, "tcp", "HTTP", "http"),
+    (8000, "tcp", "HTTP", "http"),
+    (3000, "tcp", "开发服务器", "http"),
+    (5173, "tcp", "Vite 开发服务器", "http"),
+];
+
+// 为端口建议创建的隧道
+#[derive(Debug, Clone, Serialize)]
+pub struct PortSuggestion {
+    pub service: Option<String>,
+    pub proxy_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortEvent {
+    pub protocol: &'static str,
+    pub port: u16,
+    // 监听该端口的套接字，双栈服务会有多个
+    pub sockets: Vec<LocalSocket>,
+    pub suggestion: PortSuggestion,
+    pub time: u64,
+}
+
+pub fn suggest(protocol: &str, port: u16) -> PortSuggestion {
+    match KNOWN_SERVICES
+        .iter()
+        .find(|(p, proto, _, _)| *p == port && *proto == protocol)
+    {
+        Some((_, _, name, proxy_type)) => PortSuggestion {
+            service: Some(name.to_string()),
+            proxy_type: proxy_type.to_string(),
+        },
+        None => PortSuggestion {
+            service: None,
+            proxy_type: protocol.to_string(),
+        },
+    }
+}
+
+fn is_interesting(protocol: &str, port: u16) -> bool {
+    protocol == "tcp"
+        || port < EPHEMERAL_PORT_START
+        || KNOWN_SERVICES
+            .iter()
+            .any(|(p, proto, _, _)| *p == port && *proto == protocol)
+}
+
+type PortKey = (&'static str, u16);
+
+#[derive(Default)]
+struct Watcher {
+    cancel: Option<oneshot::Sender<()>>,
+    // 监视器启动后新打开且仍在监听的端口，供晚加载的前端查询
+    opened: HashMap<PortKey, PortEvent>,
+}
+
+#[derive(Default)]
+pub struct PortWatcherState(Mutex<Watcher>);
+
+fn snapshot() -> Result<HashSet<PortKey>, String> {
+    Ok(local_ports::listening_endpoints()?
+        .into_iter()
+        .map(|(protocol, _, port)| (protocol, port))
+        .filter(|(protocol, port)| is_interesting(protocol, *port))
+        .collect())
+}
+
+async fn snapshot_async() -> Result<HashSet<PortKey>, String> {
+    tokio::task::spawn_blocking(snapshot)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// 端口有变化时才查询进程信息
+async fn sockets_by_port(keys: &HashSet<PortKey>) -> HashMap<PortKey, Vec<LocalSocket>> {
+    let sockets = tokio::task::spawn_blocking(|| local_ports::list_sockets(true))
+        .await
+        .ok()
+        .and_then(|r| r.ok())
+        .unwrap_or_default();
+    let mut grouped: HashMap<PortKey, Vec<LocalSocket>> = HashMap::new();
+    for socket in sockets {
+        let key = (socket.protocol, socket.port);
+        if keys.contains(&key) {
+            grouped.entry(key).or_default().push(socket);
+        }
+    }
+    grouped
+}
+
+async fn run<R: Runtime>(app: AppHandle<R>, mut cancel_rx: oneshot::Receiver<()>) {
+    // 启动时已在监听的端口不视为新打开
+    let mut previous = match snapshot_async().await {
+        Ok(ports) => ports,
+        Err(e) => {
+            log::warn!("端口监视器无法读取本机端口: {}", e);
+            HashSet::new()
+        }
+    };
+    log::info!("端口监视器已启动，当前监听 {} 个端口", previous.len());
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = &mut cancel_rx => break,
+        }
+
+        let current = match snapshot_async().await {
+            Ok(ports) => ports,
+            Err(e) => {
+                log::debug!("端口监视器读取失败: {}", e);
+                continue;
+            }
+        };
+        if current == previous {
+            continue;
+        }
+
+        let opened: HashSet<PortKey> = current.difference(&previous).copied().collect();
+        let closed: Vec<PortKey> = previous.difference(&current).copied().collect();
+        let mut sockets = if opened.is_empty() {
+            HashMap::new()
+        } else {
+            sockets_by_port(&opened).await
+        };
+
+        let state = app.state::<PortWatcherState>();
+        for key in opened {
+            let event = PortEvent {
+                protocol: key.0,
+                port: key.1,
+                sockets: sockets.remove(&key).unwrap_or_default(),
+                suggestion: suggest(key.0, key.1),
+                time: crate::session::now_secs(),
+            };
+            log::debug!("本地端口已打开: {}/{}", key.0, key.1);
+            let _ = app.emit("local-port-opened", &event);
+            if let Ok(mut watcher) = state.0.lock() {
+                watcher.opened.insert(key, event);
+            }
+        }
+        for key in closed {
+            let removed = state
+                .0
+                .lock()
+                .ok()
+                .and_then(|mut watcher| watcher.opened.remove(&key));
+            let event = removed.unwrap_or_else(|| PortEvent {
+                protocol: key.0,
+                port: key.1,
+                sockets: Vec::new(),
+                suggestion: suggest(key.0, key.1),
+                time: 0,
+            });
+            log::debug!("本地端口已关闭: {}/{}", key.0, key.1);
+            let _ = app.emit(
+                "local-port-closed",
+                PortEvent {
+                    time: crate::session::now_secs(),
+                    ..event
+                },
+            );
+        }
+        previous = current;
+    }
+    log::info!("端口监视器已停止");
+}
+
+pub fn start<R: Runtime>(app: &AppHandle<R>) {
+    let state = app.state::<PortWatcherState>();
+    let mut watcher = match state.0.lock() {
+        Ok(watcher) => watcher,
+        Err(_) => return,
+    };
+    if watcher.cancel.is_some() {
+        return;
+    }
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    watcher.cancel = Some(cancel_tx);
+    tauri::async_runtime::spawn(run(app.clone(), cancel_rx));
+}
+
+pub fn stop<R: Runtime>(app: &AppHandle<R>) {
+    if let Ok(mut watcher) = app.state::<PortWatcherState>().0.lock() {
+        if let Some(cancel) = watcher.cancel.take() {
+            let _ = cancel.send(());
+        }
+        watcher.opened.clear();
+    }
+}
+
+#[command]
+pub fn get_port_watcher(state: State<'_, PortWatcherState>) -> bool {
+    state
+        .0
+        .lock()
+        .map(|watcher| watcher.cancel.is_some())
+        .unwrap_or(false)
+}
+
+// 开关端口监视器并保存到配置
+#[command]
+pub fn set_port_watcher<R: Runtime>(app: AppHandle<R>, enabled: bool) -> Result<(), String> {
+    if enabled {
+        start(&app);
+    } else {
+        stop(&app);
+    }
+    let mut config = crate::load_config()?;
+    config.port_watcher = Some(enabled);
+    crate::save_config(&config)
+}
+
+// 监视器启动后新打开且仍在监听的端口
+#[command]
+pub fn get_opened_ports(state: State<'_, PortWatcherState>) -> Vec<PortEvent> {
+    let mut events: Vec<PortEvent> = state
+        .0
+        .lock()
+        .map(|watcher| watcher.opened.values().cloned().collect())
+        .unwrap_or_default();
+    events.sort_by(|a, b| b.time.cmp(&a.time));
+    events
+}
+
+// 用户忽略某个端口的建议
+#[command]
+pub fn dismiss_opened_port(state: State<'_, PortWatcherState>, protocol: String, port: u16) {
+    if let Ok(mut watcher) = state.0.lock() {
+        watcher
+            .opened
+            .retain(|(p, key_port), _| !(*p == protocol && *key_port == port));
+    }
+}
@@ -0,0 +1,343 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::session;
+
+// 首次启动时创建的默认账户
+const DEFAULT_PROFILE_ID: &str = "default";
+const DEFAULT_PROFILE_NAME: &str = "默认账户";
+const MAX_PROFILE_NAME_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileStore {
+    active: Option<String>,
+    profiles: Vec<Profile>,
+}
+
+// 返回给前端的账户概要
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSummary {
+    #[serde(flatten)]
+    pub profile: Profile,
+    pub active: bool,
+    pub session: Option<session::SessionInfo>,
+    // 由该账户启动且仍在运行的隧道
+    pub running_tunnels: Vec<String>,
+}
+
+pub struct ProfilesState(Mutex<ProfileStore>);
+
+fn profiles_root(app_dir: &Path) -> PathBuf {
+    app_dir.join("config").join("profiles")
+}
+
+fn store_path(app_dir: &Path) -> PathBuf {
+    app_dir.join("config").join("profiles.json")
+}
+
+// 每个账户独立的数据目录：会话、隧道列表缓存、隧道设置
+pub fn profile_dir(app_dir: &Path, id: &str) -> PathBuf {
+    profiles_root(app_dir).join(id)
+}
+
+fn save_store(store: &ProfileStore) -> Result<(), String> {
+    let path = store_path(&crate::get_app_dir());
+    let content =
+        serde_json::to_string_pretty(store).map_err(|e| format!("序列化账户列表失败: {}", e))?;
+    crate::write_private_file(&path, content.as_bytes())
+        .map_err(|e| format!("保存账户列表失败: {}", e))
+}
+
+impl ProfilesState {
+    // 读取账户列表，必要时创建默认账户并迁移旧版单账户会话
+    pub fn load(app_dir: &Path) -> Self {
+        let mut store = fs::read_to_string(store_path(app_dir))
+            .ok()
+            .and_then(|content| serde_json::from_str::<ProfileStore>(&content).ok())
+            .unwrap_or_default();
+
+        if store.profiles.is_empty() {
+            store.profiles.push(Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                created_at: session::now_secs(),
+            });
+        }
+        let active_exists = store
+            .active
+            .as_ref()
+            .map_or(false, |id| store.profiles.iter().any(|p| &p.id == id));
+        if !active_exists {
+            store.active = Some(store.profiles[0].id.clone());
+        }
+
+        // 旧版本会话保存在 config/session.json，迁移到当前账户目录
+        let legacy_session = app_dir.join("config").join("session.json");
+        if legacy_session.exists() {
+            let target_dir = profile_dir(
+                app_dir,
+                store.active.as_deref().unwrap_or(DEFAULT_PROFILE_ID),
+            );
+            let _ = fs::create_dir_all(&target_dir);
+            let target = target_dir.join("session.json");
+            if !target.exists() {
+                let _ = fs::rename(&legacy_session, &target);
+            } else {
+                let _ = fs::remove_file(&legacy_session);
+            }
+        }
+
+        if let Err(e) = save_store(&store) {
+            log::error!("{}", e);
+        }
+        ProfilesState(Mutex::new(store))
+    }
+}
+
+fn generate_profile_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("账户名称不能为空".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_LEN {
+        return Err(format!("账户名称不能超过 {} 个字符", MAX_PROFILE_NAME_LEN));
+    }
+    Ok(name.to_string())
+}
+
+// 当前激活的账户 ID
+pub fn active_profile_id<R: Runtime>(app: &AppHandle<R>) -> Option<String> {
+    app.try_state::<ProfilesState>()
+        .and_then(|state| state.0.lock().unwrap().active.clone())
+}
+
+// 当前激活账户的数据目录
+pub fn active_profile_dir<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+    profile_dir_of(active_profile_id(app).as_deref())
+}
+
+// 指定账户的数据目录，未指定时为默认账户
+pub fn profile_dir_of(id: Option<&str>) -> PathBuf {
+    profile_dir(&crate::get_app_dir(), id.unwrap_or(DEFAULT_PROFILE_ID))
+}
+
+// 按账户统计正在运行的隧道
+fn running_tunnels_by_profile(processes: &crate::FrpcProcesses) -> HashMap<String, Vec<String>> {
+    let mut result: HashMap<String, Vec<String>> = HashMap::new();
+    if let Ok(map) = processes.0.lock() {
+        for (id, info) in map.iter() {
+            if let Some(profile_id) = &info.profile_id {
+                result
+                    .entry(profile_id.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+        }
+    }
+    result
+}
+
+fn summaries(store: &ProfileStore, processes: &crate::FrpcProcesses) -> Vec<ProfileSummary> {
+    let app_dir = crate::get_app_dir();
+    let mut running = running_tunnels_by_profile(processes);
+    store
+        .profiles
+        .iter()
+        .map(|profile| ProfileSummary {
+            profile: profile.clone(),
+            active: store.active.as_deref() == Some(profile.id.as_str()),
+            session: session::load_session_from(&profile_dir(&app_dir, &profile.id))
+                .map(|s| s.info()),
+            running_tunnels: running.remove(&profile.id).unwrap_or_default(),
+        })
+        .collect()
+}
+
+#[command]
+pub fn list_profiles(
+    state: State<'_, ProfilesState>,
+    processes: State<'_, crate::FrpcProcesses>,
+) -> Vec<ProfileSummary> {
+    let store = state.0.lock().unwrap();
+    summaries(&store, &processes)
+}
+
+#[command]
+pub fn add_profile(state: State<'_, ProfilesState>, name: String) -> Result<Profile, String> {
+    let name = validate_name(&name)?;
+    let mut store = state.0.lock().unwrap();
+    if store.profiles.iter().any(|p| p.name == name) {
+        return Err("已存在同名账户".to_string());
+    }
+
+    let profile = Profile {
+        id: generate_profile_id(),
+        name,
+        created_at: session::now_secs(),
+    };
+    fs::create_dir_all(profile_dir(&crate::get_app_dir(), &profile.id))
+        .map_err(|e| format!("创建账户目录失败: {}", e))?;
+    store.profiles.push(profile.clone());
+    save_store(&store)?;
+    Ok(profile)
+}
+
+#[command]
+pub fn rename_profile(
+    state: State<'_, ProfilesState>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    let name = validate_name(&name)?;
+    let mut store = state.0.lock().unwrap();
+    if store.profiles.iter().any(|p| p.name == name && p.id != id) {
+        return Err("已存在同名账户".to_string());
+    }
+    let profile = store
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or("账户不存在")?;
+    profile.name = name;
+    save_store(&store)
+}
+
+#[command]
+pub fn remove_profile(
+    state: State<'_, ProfilesState>,
+    processes: State<'_, crate::FrpcProcesses>,
+    id: String,
+) -> Result<(), String> {
+    let mut store = state.0.lock().unwrap();
+    if !store.profiles.iter().any(|p| p.id == id) {
+        return Err("账户不存在".to_string());
+    }
+    if store.active.as_deref() == Some(id.as_str()) {
+        return Err("不能删除当前正在使用的账户，请先切换到其他账户".to_string());
+    }
+    if running_tunnels_by_profile(&processes).contains_key(&id) {
+        return Err("该账户仍有运行中的隧道，请先停止".to_string());
+    }
+
+    store.profiles.retain(|p| p.id != id);
+    save_store(&store)?;
+
+    let dir = profile_dir(&crate::get_app_dir(), &id);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("删除账户数据失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 切换账户：其他账户启动的隧道保持运行，只替换当前会话
+#[command]
+pub async fn switch_profile<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ProfilesState>,
+    processes: State<'_, crate::FrpcProcesses>,
+    id: String,
+) -> Result<Vec<ProfileSummary>, String> {
+    {
+        let mut store = state.0.lock().unwrap();
+        if !store.profiles.iter().any(|p| p.id == id) {
+            return Err("账户不存在".to_string());
+        }
+        store.active = Some(id.clone());
+        save_store(&store)?;
+    }
+
+    session::unload_session(&app);
+    session::restore_and_validate(app.clone()).await;
+
+    let summaries = {
+        let store = state.0.lock().unwrap();
+        summaries(&store, &processes)
+    };
+    let _ = app.emit("profile-switched", &id);
+    Ok(summaries)
+}
+
+// 缓存当前账户的隧道列表
+#[command]
+pub fn save_profile_tunnels<R: Runtime>(
+    app: AppHandle<R>,
+    tunnels: serde_json::Value,
+) -> Result<(), String> {
+    let path = active_profile_dir(&app).join("tunnels.json");
+    let content = serde_json::to_vec(&tunnels).map_err(|e| e.to_string())?;
+    crate::write_private_file(&path, &content).map_err(|e| format!("缓存隧道列表失败: {}", e))
+}
+
+#[command]
+pub fn get_profile_tunnels<R: Runtime>(app: AppHandle<R>) -> Result<serde_json::Value, String> {
+    let path = active_profile_dir(&app).join("tunnels.json");
+    match fs::read_to_string(&path) {
+        Ok(content) => {
+            serde_json::from_str(&content).map_err(|e| format!("解析隧道缓存失败: {}", e))
+        }
+        Err(_) => Ok(serde_json::Value::Null),
+    }
+}
+
+fn load_tunnel_settings(dir: &Path) -> HashMap<String, serde_json::Value> {
+    fs::read_to_string(dir.join("tunnel_settings.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// 读取当前账户下某条隧道（或全部隧道）的设置
+#[command]
+pub fn get_tunnel_settings<R: Runtime>(
+    app: AppHandle<R>,
+    tunnel_id: Option<String>,
+) -> serde_json::Value {
+    let settings = load_tunnel_settings(&active_profile_dir(&app));
+    match tunnel_id {
+        Some(id) => settings
+            .get(&id)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null),
+        None => serde_json::to_value(settings).unwrap_or(serde_json::Value::Null),
+    }
+}
+
+#[command]
+pub fn set_tunnel_settings<R: Runtime>(
+    app: AppHandle<R>,
+    tunnel_id: String,
+    settings: serde_json::Value,
+) -> Result<(), String> {
+    let dir = active_profile_dir(&app);
+    let mut all = load_tunnel_settings(&dir);
+    if settings.is_null() {
+        all.remove(&tunnel_id);
+    } else {
+        all.insert(tunnel_id, settings);
+    }
+    let content = serde_json::to_vec_pretty(&all).map_err(|e| e.to_string())?;
+    crate::write_private_file(&dir.join("tunnel_settings.json"), &content)
+        .map_err(|e| format!("保存隧道设置失败: {}", e))
+}
@@ -0,0 +1,117 @@
+// 日志脱敏工具：避免用户密钥、Authorization 等敏感信息出现在日志或输出中
+
+// 用户密钥为 32 位十六进制字符串
+const TOKEN_LEN: usize = 32;
+// 安全登录 / OAuth 返回的 Authorization 前缀
+const AUTHORIZATION_PREFIX: &str = "OPENFRP";
+
+// 将密钥遮盖为仅保留首尾各 4 位的形式
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}****{}", head, tail)
+}
+
+// 替换文本中出现的已知密钥
+pub fn redact_known(text: &str, secrets: &[&str]) -> String {
+    let mut result = text.to_string();
+    for secret in secrets {
+        // 过短的字符串容易误伤正常内容，直接跳过
+        if secret.len() < 6 {
+            continue;
+        }
+        result = result.replace(secret, &mask_secret(secret));
+    }
+    redact_secrets(&result)
+}
+
+// 按特征遮盖文本中疑似密钥的内容：32 位十六进制用户密钥以及 OPENFRP 开头的 Authorization
+pub fn redact_secrets(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if rest.starts_with(AUTHORIZATION_PREFIX) {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+                .unwrap_or(rest.len());
+            if len > AUTHORIZATION_PREFIX.len() {
+                result.push_str(AUTHORIZATION_PREFIX);
+                result.push_str("****");
+                rest = &rest[len..];
+                continue;
+            }
+        }
+
+        let hex_len = rest
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(rest.len());
+        if hex_len >= TOKEN_LEN {
+            result.push_str(&mask_secret(&rest[..hex_len]));
+            rest = &rest[hex_len..];
+            continue;
+        }
+
+        // 非密钥内容原样保留，跳过整段字母数字以免截断单词中间；
+        // 单词中紧跟在其他字母之后的密钥只跳到密钥开头，下一轮再遮盖
+        let word_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let step = match hex_run_start(&rest[..word_len]) {
+            Some(start) if start > 0 => start,
+            _ if word_len > 0 => word_len,
+            _ => rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1),
+        };
+        result.push_str(&rest[..step]);
+        rest = &rest[step..];
+    }
+
+    result
+}
+
+// 单词中第一段长度达到密钥长度的十六进制字符的起始位置
+fn hex_run_start(word: &str) -> Option<usize> {
+    let mut run_start = 0;
+    for (i, c) in word.char_indices() {
+        if !c.is_ascii_hexdigit() {
+            run_start = i + c.len_utf8();
+        } else if i + 1 - run_start >= TOKEN_LEN {
+            return Some(run_start);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn masks_standalone_token() {
+        assert_eq!(
+            redact_secrets(&format!("-u {} -p 1", TOKEN)),
+            "-u 0123****cdef -p 1"
+        );
+    }
+
+    #[test]
+    fn masks_token_after_letters() {
+        assert_eq!(
+            redact_secrets(&format!("user{}", TOKEN)),
+            "user0123****cdef"
+        );
+        assert_eq!(redact_secrets(&format!("xyz{}1", TOKEN)), "xyz0123****def1");
+    }
+
+    #[test]
+    fn keeps_ordinary_words() {
+        let text = "login to server success, proxy [abcdef] started";
+        assert_eq!(redact_secrets(text), text);
+    }
+}
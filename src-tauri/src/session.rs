@@ -0,0 +1,514 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::oauth::OAuthResponse;
+
+// 会话 ID 有效期 8 小时
+const SESSION_TTL_SECS: u64 = 8 * 60 * 60;
+// 安全登录 Authorization 最长有效期 30 天
+const SECURITY_LOGIN_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+// 过期前多久发出 session-expiring 提醒
+const EXPIRY_WARNING_SECS: u64 = 30 * 60;
+// 过期检查间隔
+const CHECK_INTERVAL_SECS: u64 = 60;
+
+const USER_INFO_URL: &str = "https://api.openfrp.net/frp/api/getUserInfo";
+
+// 会话来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    // OAuth code 登录
+    OAuth,
+    // 第三方客户端安全登录（粘贴 Authorization）
+    SecurityLogin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub kind: SessionKind,
+    pub authorization: String,
+    // 会话 ID（仅 OAuth 登录返回）
+    pub session_id: Option<String>,
+    pub user_id: Option<u64>,
+    pub username: Option<String>,
+    // 签发时间与预计过期时间（Unix 秒）
+    pub issued_at: u64,
+    pub expires_at: u64,
+    // 本次运行是否已经发送过提醒，不写入文件
+    #[serde(skip)]
+    warned: bool,
+    #[serde(skip)]
+    expired_notified: bool,
+}
+
+// 返回给前端的会话信息，不包含 Authorization
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub kind: SessionKind,
+    pub user_id: Option<u64>,
+    pub username: Option<String>,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    pub remaining_secs: u64,
+}
+
+#[derive(Default)]
+pub struct SessionState(Mutex<Option<Session>>);
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 从 Authorization（OPENFRP + JWT）中读取 exp 字段
+pub fn authorization_expiry(authorization: &str) -> Option<u64> {
+    let jwt = authorization.trim().trim_start_matches("OPENFRP");
+    let payload = jwt.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims["exp"].as_u64()
+}
+
+impl Session {
+    pub fn new(kind: SessionKind, authorization: String, session_id: Option<String>) -> Self {
+        let issued_at = now_secs();
+        let ttl = match kind {
+            SessionKind::OAuth => SESSION_TTL_SECS,
+            SessionKind::SecurityLogin => SECURITY_LOGIN_TTL_SECS,
+        };
+        // 以 JWT 中的 exp 为准，但不超过该登录方式的最长有效期
+        let expires_at = authorization_expiry(&authorization)
+            .map(|exp| exp.min(issued_at + ttl))
+            .unwrap_or(issued_at + ttl);
+        Session {
+            kind,
+            authorization,
+            session_id,
+            user_id: None,
+            username: None,
+            issued_at,
+            expires_at,
+            warned: false,
+            expired_notified: false,
+        }
+    }
+
+    pub fn from_oauth(response: &OAuthResponse) -> Self {
+        let session_id = Some(response.data.clone()).filter(|s| !s.is_empty());
+        Session::new(
+            SessionKind::OAuth,
+            response.authorization.clone(),
+            session_id,
+        )
+    }
+
+    fn remaining_secs(&self) -> u64 {
+        self.expires_at.saturating_sub(now_secs())
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            kind: self.kind,
+            user_id: self.user_id,
+            username: self.username.clone(),
+            issued_at: self.issued_at,
+            expires_at: self.expires_at,
+            remaining_secs: self.remaining_secs(),
+        }
+    }
+}
+
+// 会话保存在各账户的数据目录中
+fn session_path(dir: &Path) -> PathBuf {
+    dir.join("session.json")
+}
+
+fn save_session(dir: &Path, session: &Session) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(session).map_err(|e| format!("序列化会话失败: {}", e))?;
+    // 会话中包含 Authorization，仅允许当前用户读写
+    crate::write_private_file(&session_path(dir), content.as_bytes())
+        .map_err(|e| format!("保存会话失败: {}", e))
+}
+
+// 读取指定账户目录下保存的会话
+pub fn load_session_from(dir: &Path) -> Option<Session> {
+    let content = fs::read_to_string(session_path(dir)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn delete_session_file(dir: &Path) {
+    let path = session_path(dir);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("删除会话文件失败: {}", e);
+        }
+    }
+}
+
+// 记录新的会话并持久化
+pub fn store_session<R: Runtime>(
+    app: &AppHandle<R>,
+    session: Session,
+) -> Result<SessionInfo, String> {
+    save_session(&crate::profiles::active_profile_dir(app), &session)?;
+    let info = session.info();
+    if let Some(state) = app.try_state::<SessionState>() {
+        *state.0.lock().unwrap() = Some(session);
+    }
+    let _ = app.emit("session-updated", &info);
+    Ok(info)
+}
+
+// 当前会话的 Authorization，用于日志与诊断信息脱敏
+pub fn current_authorization<R: Runtime>(app: &AppHandle<R>) -> Option<String> {
+    app.try_state::<SessionState>().and_then(|state| {
+        state
+            .0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|s| s.authorization.clone())
+    })
+}
+
+// 仅清除内存中的会话（切换账户时使用），磁盘上的会话保留
+pub fn unload_session<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(state) = app.try_state::<SessionState>() {
+        state.0.lock().unwrap().take();
+    }
+}
+
+// 清除内存与磁盘中的会话
+pub fn clear_session<R: Runtime>(app: &AppHandle<R>) {
+    unload_session(app);
+    delete_session_file(&crate::profiles::active_profile_dir(app));
+}
+
+// getUserInfo 的结果，服务端可能在响应头中下发新的 Authorization
+pub struct UserInfoResponse {
+    pub flag: bool,
+    pub msg: String,
+    pub data: serde_json::Value,
+    pub new_authorization: Option<String>,
+}
+
+// 使用 Authorization 请求 getUserInfo
+pub async fn fetch_user_info(authorization: &str) -> Result<UserInfoResponse, String> {
+    let user_agent = format!(
+        "OpenFrp-CPL/{}-{}",
+        std::env::consts::OS,
+        env!("CARGO_PKG_VERSION")
+    );
+    let client = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let res = client
+        .post(USER_INFO_URL)
+        .header("Authorization", authorization)
+        .send()
+        .await
+        .map_err(|e| format!("请求用户信息失败: {}", e))?;
+
+    let new_authorization = res
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+        .filter(|v| !v.is_empty() && v != authorization);
+
+    let json = res
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("解析用户信息失败: {}", e))?;
+
+    Ok(UserInfoResponse {
+        flag: json["flag"].as_bool().unwrap_or(false),
+        msg: json["msg"].as_str().unwrap_or("").to_string(),
+        data: json["data"].clone(),
+        new_authorization,
+    })
+}
+
+// 用 getUserInfo 的结果补全会话信息
+fn apply_user_info(session: &mut Session, response: &UserInfoResponse) {
+    if let Some(id) = response.data["id"].as_u64() {
+        session.user_id = Some(id);
+    }
+    if let Some(name) = response.data["username"].as_str() {
+        session.username = Some(name.to_string());
+    }
+    if let Some(authorization) = &response.new_authorization {
+        // 服务端下发了新的 Authorization，有效期随之刷新
+        let refreshed = Session::new(
+            session.kind,
+            authorization.clone(),
+            session.session_id.clone(),
+        );
+        session.authorization = refreshed.authorization;
+        session.expires_at = refreshed.expires_at.max(session.expires_at);
+        session.warned = false;
+    }
+}
+
+// 指定账户目录下仍然有效的 Authorization，用于以该账户获取隧道配置
+pub fn profile_authorization(dir: &Path) -> Result<String, String> {
+    let session = load_session_from(dir).ok_or("账户未登录")?;
+    if session.remaining_secs() == 0 {
+        return Err("会话已过期".to_string());
+    }
+    Ok(session.authorization)
+}
+
+fn emit_expired<R: Runtime>(app: &AppHandle<R>, reason: &str) {
+    let _ = app.emit("session-expired", serde_json::json!({ "reason": reason }));
+}
+
+// 启动时恢复会话，并用一次 getUserInfo 确认其仍然有效
+pub async fn restore_and_validate<R: Runtime>(app: AppHandle<R>) {
+    // 验证期间可能切换账户，之后的读写都针对开始验证时的账户
+    let profile_id = crate::profiles::active_profile_id(&app);
+    let dir = crate::profiles::active_profile_dir(&app);
+    let mut session = match load_session_from(&dir) {
+        Some(session) => session,
+        None => return,
+    };
+
+    if session.remaining_secs() == 0 {
+        log::info!("已保存的会话已过期");
+        clear_session(&app);
+        emit_expired(&app, "会话已过期");
+        return;
+    }
+
+    let result = fetch_user_info(&session.authorization).await;
+    // 已切换到其他账户时只更新原账户保存的会话，不影响当前账户的内存状态与界面
+    let still_active = crate::profiles::active_profile_id(&app) == profile_id;
+    match result {
+        Ok(response) if response.flag => {
+            apply_user_info(&mut session, &response);
+            if let Err(e) = save_session(&dir, &session) {
+                log::error!("保存会话失败: {}", e);
+                return;
+            }
+            if still_active {
+                let info = session.info();
+                if let Some(state) = app.try_state::<SessionState>() {
+                    *state.0.lock().unwrap() = Some(session);
+                }
+                let _ = app.emit("session-updated", &info);
+                let _ = app.emit("session-validated", &info);
+            }
+        }
+        Ok(response) => {
+            log::warn!("已保存的会话无效: {}", response.msg);
+            delete_session_file(&dir);
+            if still_active {
+                unload_session(&app);
+                emit_expired(&app, &response.msg);
+            }
+        }
+        Err(e) => {
+            // 网络错误时保留会话，等待下次检查
+            log::warn!("验证会话失败: {}", e);
+            if still_active {
+                if let Some(state) = app.try_state::<SessionState>() {
+                    *state.0.lock().unwrap() = Some(session);
+                }
+            }
+        }
+    }
+}
+
+// 周期性检查会话有效期，过期前发送 session-expiring，过期后发送 session-expired
+pub fn spawn_expiry_monitor<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+
+            let state = app.state::<SessionState>();
+            let mut guard = state.0.lock().unwrap();
+            let session = match guard.as_mut() {
+                Some(session) => session,
+                None => continue,
+            };
+
+            let remaining = session.remaining_secs();
+            if remaining == 0 {
+                if !session.expired_notified {
+                    session.expired_notified = true;
+                    emit_expired(&app, "会话已过期");
+                }
+            } else if remaining <= EXPIRY_WARNING_SECS && !session.warned {
+                session.warned = true;
+                let _ = app.emit("session-expiring", session.info());
+            }
+        }
+    });
+}
+
+#[command]
+pub fn get_session_info(state: State<'_, SessionState>) -> Option<SessionInfo> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|session| session.info())
+}
+
+// 安全登录失败的原因，便于前端给出准确提示
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SecurityLoginError {
+    // 粘贴内容不是有效的 Authorization
+    Malformed { message: String },
+    // Authorization 已超过有效期
+    Expired { message: String, expired_at: u64 },
+    // 服务端拒绝（已在面板注销或被撤销）
+    Revoked { message: String },
+    // 网络或服务端异常，Authorization 本身可能仍然有效
+    Network { message: String },
+    // 验证通过但保存会话失败
+    Storage { message: String },
+}
+
+fn malformed(message: &str) -> SecurityLoginError {
+    SecurityLoginError::Malformed {
+        message: message.to_string(),
+    }
+}
+
+// 整理用户粘贴的内容：去掉首尾空白、引号以及可能一并复制的 "Authorization:" 前缀
+fn normalize_authorization(input: &str) -> Result<String, SecurityLoginError> {
+    let mut value = input.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some(idx) = value.find(':') {
+        if value[..idx].trim().eq_ignore_ascii_case("authorization") {
+            value = value[idx + 1..].trim();
+        }
+    }
+
+    if value.is_empty() {
+        return Err(malformed("请粘贴从管理面板复制的 Authorization"));
+    }
+    if value.chars().any(|c| c.is_whitespace()) {
+        return Err(malformed(
+            "Authorization 中不应包含空白字符，请检查是否复制完整",
+        ));
+    }
+    if !value.starts_with("OPENFRP") {
+        return Err(malformed("Authorization 应以 OPENFRP 开头"));
+    }
+
+    let jwt = &value["OPENFRP".len()..];
+    let parts: Vec<&str> = jwt.split('.').collect();
+    if parts.len() != 3 || parts.iter().any(|part| part.is_empty()) {
+        return Err(malformed("Authorization 不完整，请重新复制"));
+    }
+    if authorization_expiry(value).is_none() {
+        return Err(malformed("无法解析 Authorization 的内容，请重新复制"));
+    }
+    Ok(value.to_string())
+}
+
+// 第三方客户端安全登录：校验用户从面板复制的 Authorization 并保存为当前账户的会话
+#[command]
+pub async fn security_login<R: Runtime>(
+    app: AppHandle<R>,
+    authorization: String,
+) -> Result<SessionInfo, SecurityLoginError> {
+    let authorization = normalize_authorization(&authorization)?;
+
+    if let Some(exp) = authorization_expiry(&authorization) {
+        if exp <= now_secs() {
+            return Err(SecurityLoginError::Expired {
+                message: "Authorization 已过期，请在管理面板重新获取".to_string(),
+                expired_at: exp,
+            });
+        }
+    }
+
+    let response = fetch_user_info(&authorization)
+        .await
+        .map_err(|message| SecurityLoginError::Network { message })?;
+    if !response.flag {
+        return Err(SecurityLoginError::Revoked {
+            message: if response.msg.is_empty() {
+                "Authorization 已失效，请在管理面板重新获取".to_string()
+            } else {
+                format!("Authorization 已失效: {}", response.msg)
+            },
+        });
+    }
+
+    let mut session = Session::new(SessionKind::SecurityLogin, authorization, None);
+    apply_user_info(&mut session, &response);
+    store_session(&app, session).map_err(|message| SecurityLoginError::Storage { message })
+}
+
+// 退出登录时是否默认停止该账户启动的隧道
+#[command]
+pub fn get_stop_tunnels_on_logout() -> Result<bool, String> {
+    Ok(crate::load_config()?
+        .stop_tunnels_on_logout
+        .unwrap_or(false))
+}
+
+#[command]
+pub fn set_stop_tunnels_on_logout(enabled: bool) -> Result<(), String> {
+    let mut config = crate::load_config()?;
+    config.stop_tunnels_on_logout = Some(enabled);
+    crate::save_config(&config)
+}
+
+// 退出登录：清除当前账户保存的凭据，按设置停止该账户启动的隧道
+#[command]
+pub async fn logout<R: Runtime>(
+    app: AppHandle<R>,
+    processes: State<'_, crate::FrpcProcesses>,
+    stop_tunnels: Option<bool>,
+) -> Result<(), String> {
+    clear_session(&app);
+
+    let stop_tunnels = match stop_tunnels {
+        Some(stop) => stop,
+        None => crate::load_config()?
+            .stop_tunnels_on_logout
+            .unwrap_or(false),
+    };
+    // 暂停中的隧道没有进程，本地服务恢复后监控会以该账户重新启动它，退出登录后一律取消；
+    // 停止隧道时同时取消其余隧道的监控
+    let profile_id = crate::profiles::active_profile_id(&app);
+    crate::health::unwatch_profile(&app, profile_id.as_deref(), !stop_tunnels);
+    if stop_tunnels {
+        let drained: Vec<_> = match processes.0.lock() {
+            Ok(mut map) => {
+                let ids: Vec<String> = map
+                    .iter()
+                    .filter(|(_, info)| info.profile_id == profile_id)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                ids.iter().filter_map(|id| map.remove(id)).collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        for process_info in drained {
+            crate::terminate_process(process_info);
+        }
+    }
+
+    let _ = app.emit("session-logout", stop_tunnels);
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 隧道启动配置文件所在目录
+fn tunnel_config_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("config")
+}
+
+// 检查隧道 ID，只接受以逗号分隔的数字，避免异常内容被原样传给接口或 frpc
+pub fn validate_tunnel_id(tunnel_id: &str) -> Result<(), String> {
+    if tunnel_id.is_empty()
+        || !tunnel_id
+            .split(',')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(format!("无效的隧道 ID: {}", tunnel_id));
+    }
+    Ok(())
+}
+
+// 从节点配置中挑出指定隧道：第一段为 frpc 公共配置，其余每段为一个隧道（与“配置文件启动”页面一致）
+fn select_proxies(node_conf: &str, proxy_names: &[String]) -> Option<String> {
+    let mut sections = node_conf.split("\n\n[[proxies]]\n");
+    let mut content = sections.next()?.to_string();
+    let mut found = 0;
+    for section in sections {
+        if proxy_names
+            .iter()
+            .any(|name| section.starts_with(&format!("name = \"{}\"", name)))
+        {
+            content.push_str("\n\n[[proxies]]\n");
+            content.push_str(section);
+            found += 1;
+        }
+    }
+    if found == 0 {
+        return None;
+    }
+    Some(content)
+}
+
+// 通过 getUserProxies 与 getNodeConf 获取隧道的 frpc 配置，多个隧道必须位于同一节点
+pub async fn fetch_tunnel_config(authorization: &str, tunnel_id: &str) -> Result<String, String> {
+    validate_tunnel_id(tunnel_id)?;
+    let ids: Vec<&str> = tunnel_id.split(',').collect();
+
+    let proxies =
+        crate::api_proxy::call_api(authorization, "getUserProxies", serde_json::json!({})).await?;
+    let mut node_id = None;
+    let mut proxy_names = Vec::new();
+    for id in &ids {
+        let proxy = proxies["data"]["list"]
+            .as_array()
+            .and_then(|list| {
+                list.iter()
+                    .find(|p| p["id"].to_string().trim_matches('"') == *id)
+            })
+            .ok_or_else(|| format!("未找到隧道 {}", id))?;
+        let nid = proxy["nid"].as_u64().ok_or("隧道缺少节点信息")?;
+        if node_id.is_some_and(|node_id| node_id != nid) {
+            return Err("同时启动的隧道必须位于同一节点".to_string());
+        }
+        node_id = Some(nid);
+        let name = proxy["proxyName"].as_str().ok_or("隧道缺少名称")?;
+        proxy_names.push(name.to_string());
+    }
+
+    let response = crate::api_proxy::call_api(
+        authorization,
+        "getNodeConf",
+        serde_json::json!({ "node_id": node_id }),
+    )
+    .await?;
+    let node_conf = response["data"].as_str().ok_or("节点配置格式错误")?;
+    select_proxies(node_conf, &proxy_names).ok_or_else(|| "节点配置中没有该隧道".to_string())
+}
+
+// 写入隧道启动配置文件，frpc 通过 -c 读取，避免用户密钥出现在进程命令行中。
+// 文件仅当前用户可读写（Windows 下应用目录位于当前用户的 LocalAppData，继承其权限），
+// 文件名带时间戳，重启同一隧道时旧进程退出不会删掉新文件
+pub fn write_tunnel_config(app_dir: &Path, id: &str, content: &str) -> Result<PathBuf, String> {
+    let safe_id: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let path = tunnel_config_dir(app_dir).join(format!("tunnel_{}_{}.toml", safe_id, stamp));
+    crate::write_private_file(&path, content.as_bytes()).map_err(|e| {
+        let _ = fs::remove_file(&path);
+        format!("写入隧道配置文件失败: {}", e)
+    })?;
+    Ok(path)
+}
+
+// 删除隧道启动配置文件
+pub fn remove_tunnel_config(path: &Path) {
+    if path.exists() {
+        if let Err(e) = fs::remove_file(path) {
+            log::warn!("删除隧道配置文件失败: {}", e);
+        }
+    }
+}
+
+// 清理上次运行未能删除的隧道配置文件（如程序崩溃），其中含有用户密钥
+pub fn clean_stale_tunnel_configs(app_dir: &Path) {
+    if let Ok(entries) = fs::read_dir(tunnel_config_dir(app_dir)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("tunnel_") && name.ends_with(".toml") {
+                remove_tunnel_config(&entry.path());
+            }
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Manager, Runtime};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use crate::diagnostics::RecentLogs;
+use crate::fingerprint::{self, ServiceInfo};
+use crate::ping::{self, AddressFamily};
+use crate::udp_probe::{self, UdpProbeKind};
+
+// 连接与请求超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+// 等待服务端主动发送欢迎信息的时间
+const BANNER_TIMEOUT: Duration = Duration::from_millis(1500);
+const BANNER_MAX_LEN: usize = 256;
+// UDP 探测的等待时间与次数
+const UDP_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const UDP_PROBE_ATTEMPTS: u32 = 3;
+
+// frpc 日志中表示登录 / 启动结果的关键字
+const LOGIN_SUCCESS_KEYWORDS: &[&str] = &["login to server success", "登录成功"];
+const LOGIN_FAILED_KEYWORDS: &[&str] = &[
+    "login to server failed",
+    "登录失败",
+    "start error",
+    "启动失败",
+    "authorization failed",
+];
+
+// 需要验证的隧道，字段与 getUserProxies 返回的隧道一致，其余字段忽略
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyTarget {
+    pub proxy_type: String,
+    pub local_ip: String,
+    pub local_port: u16,
+    pub connect_address: Option<String>,
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub remote_port: u16,
+}
+
+// 失败的环节
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyFailure {
+    // 隧道未在运行
+    NotRunning,
+    // 本地服务未启动
+    LocalServiceDown,
+    // frpc 未能登录服务器或启动隧道
+    NotLoggedIn,
+    // 公网地址无法解析
+    DnsNotResolving,
+    // 远程端口无法连接
+    RemotePortClosed,
+    // 能连上远程端口，但到达的不是本地服务
+    ServiceMismatch,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HopResult {
+    pub hop: &'static str,
+    // None 表示该环节被跳过
+    pub ok: Option<bool>,
+    pub detail: String,
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub ok: bool,
+    // ok 为 false 且 failure 为空表示无法确认，例如 UDP 服务未回复探测
+    pub failure: Option<VerifyFailure>,
+    pub message: String,
+    pub hops: Vec<HopResult>,
+}
+
+struct Verifier {
+    hops: Vec<HopResult>,
+}
+
+impl Verifier {
+    fn hop(
+        &mut self,
+        hop: &'static str,
+        ok: Option<bool>,
+        detail: String,
+        latency_ms: Option<f64>,
+    ) {
+        self.hops.push(HopResult {
+            hop,
+            ok,
+            detail,
+            latency_ms,
+        });
+    }
+
+    fn fail(self, failure: VerifyFailure, message: String) -> VerifyReport {
+        VerifyReport {
+            ok: false,
+            failure: Some(failure),
+            message,
+            hops: self.hops,
+        }
+    }
+}
+
+// 连接后读取服务端主动发送的欢迎信息（例如 SSH 版本号），没有则为空
+async fn connect_and_read_banner(addr: SocketAddr) -> Result<(f64, Option<Vec<u8>>), String> {
+    let start = Instant::now();
+    let mut stream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Err(format!("连接超时（{}ms）", CONNECT_TIMEOUT.as_millis())),
+    };
+    let latency = start.elapsed().as_secs_f64() * 1000.0;
+
+    let mut buf = vec![0u8; BANNER_MAX_LEN];
+    match tokio::time::timeout(BANNER_TIMEOUT, stream.read(&mut buf)).await {
+        // 对端立即关闭连接
+        Ok(Ok(0)) => Ok((latency, Some(Vec::new()))),
+        Ok(Ok(len)) => Ok((latency, Some(buf[..len].to_vec()))),
+        Ok(Err(e)) => Err(e.to_string()),
+        // 服务端等待客户端先发送数据
+        Err(_) => Ok((latency, None)),
+    }
+}
+
+fn first_line(banner: &[u8]) -> String {
+    String::from_utf8_lossy(banner)
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+// 欢迎信息中每次连接都相同的部分：SSH、MySQL 比较服务与版本，其余比较首行第一个词。
+// MySQL 握手包带连接 ID 与随机数，POP3 等欢迎信息带时间戳，不能逐字比较
+fn banner_signature(banner: &[u8]) -> String {
+    match fingerprint::parse_banner(banner) {
+        ServiceInfo::Ssh { version } => format!("ssh {}", version),
+        ServiceInfo::Mysql { version } => format!("mysql {}", version.unwrap_or_default()),
+        _ => first_line(banner)
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_string(),
+    }
+}
+
+// 解析 connectAddress（host:port），缺少端口时使用 remotePort
+fn split_address(address: &str, default_port: u16) -> Option<(String, u16)> {
+    let address = address.trim();
+    let address = address
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(address);
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.starts_with('[') => Some((
+            host.trim_matches(|c| c == '[' || c == ']').to_string(),
+            port.parse().ok()?,
+        )),
+        _ if default_port > 0 => Some((address.to_string(), default_port)),
+        _ => None,
+    }
+}
+
+// URL 中的 IPv6 地址需要加方括号
+fn url_host(host: &str) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+// 按本地端口选择 UDP 探测方式，未知服务发送原始负载，收到任何回复即视为可达
+fn udp_probe_kind(local_port: u16) -> UdpProbeKind {
+    match local_port {
+        53 => UdpProbeKind::Dns { name: None },
+        19132 | 19133 => UdpProbeKind::Bedrock,
+        27015 => UdpProbeKind::A2sInfo,
+        _ => UdpProbeKind::Raw {
+            payload: Some("\n".to_string()),
+            payload_hex: None,
+        },
+    }
+}
+
+// 向远程地址发送 UDP 探测，返回首次收到有效回复的耗时
+async fn probe_udp(addr: SocketAddr, kind: &UdpProbeKind) -> Result<f64, String> {
+    let payload = udp_probe::raw_payload(kind)?;
+    let mut last_error = "未收到回复".to_string();
+    for _ in 0..UDP_PROBE_ATTEMPTS {
+        match tokio::time::timeout(
+            UDP_PROBE_TIMEOUT,
+            udp_probe::probe_once(addr, kind, &payload),
+        )
+        .await
+        {
+            Ok(Ok((latency, _))) => return Ok(latency),
+            Ok(Err(e)) => last_error = e,
+            Err(_) => last_error = "未收到回复".to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+// domain 字段可能是 JSON 数组字符串，也可能是逗号分隔的域名
+fn parse_domains(domain: &str) -> Vec<String> {
+    serde_json::from_str::<Vec<String>>(domain)
+        .unwrap_or_else(|_| domain.split(',').map(|d| d.trim().to_string()).collect())
+        .into_iter()
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+// 从 frpc 日志判断登录状态：Some(true) 已登录，Some(false) 登录失败，None 无法判断
+fn login_state(lines: &[String]) -> Option<bool> {
+    lines.iter().rev().find_map(|line| {
+        let line = line.to_lowercase();
+        if LOGIN_FAILED_KEYWORDS.iter().any(|k| line.contains(k)) {
+            Some(false)
+        } else if LOGIN_SUCCESS_KEYWORDS.iter().any(|k| line.contains(k)) {
+            Some(true)
+        } else {
+            None
+        }
+    })
+}
+
+async fn http_status(url: &str) -> Result<(f64, u16), String> {
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let start = Instant::now();
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    Ok((
+        start.elapsed().as_secs_f64() * 1000.0,
+        response.status().as_u16(),
+    ))
+}
+
+pub fn is_running<R: Runtime>(app: &AppHandle<R>, id: &str) -> bool {
+    let processes = app.state::<crate::FrpcProcesses>();
+    let mut map = match processes.0.lock() {
+        Ok(map) => map,
+        Err(_) => return false,
+    };
+    map.get_mut(id)
+        .map_or(false, |info| matches!(info.child.try_wait(), Ok(None)))
+}
+
+// 验证运行中的隧道是否端到端可用，并指出失败的环节
+#[command]
+pub async fn verify_tunnel<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    proxy: VerifyTarget,
+) -> Result<VerifyReport, String> {
+    let mut v = Verifier { hops: Vec::new() };
+    let proxy_type = proxy.proxy_type.to_lowercase();
+
+    // 1. 隧道进程
+    if !is_running(&app, &id) {
+        v.hop("process", Some(false), "隧道未在运行".to_string(), None);
+        return Ok(v.fail(VerifyFailure::NotRunning, "隧道未在运行".to_string()));
+    }
+    v.hop("process", Some(true), "frpc 进程运行中".to_string(), None);
+
+    // 2. 本地服务
+    let local_addr = ping::resolve(&proxy.local_ip, proxy.local_port, AddressFamily::Any)
+        .await
+        .map(|(addrs, _)| addrs[0]);
+    let local_banner = if proxy_type == "udp" {
+        v.hop(
+            "local",
+            None,
+            "UDP 服务无法通过连接检测，请使用 UDP 探测".to_string(),
+            None,
+        );
+        None
+    } else {
+        let result = match local_addr {
+            Ok(addr) => connect_and_read_banner(addr).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok((latency, banner)) => {
+                v.hop(
+                    "local",
+                    Some(true),
+                    format!("{}:{} 可以连接", proxy.local_ip, proxy.local_port),
+                    Some(latency),
+                );
+                banner
+            }
+            Err(e) => {
+                let message = format!(
+                    "本地服务 {}:{} 无法连接: {}",
+                    proxy.local_ip, proxy.local_port, e
+                );
+                v.hop("local", Some(false), message.clone(), None);
+                return Ok(v.fail(VerifyFailure::LocalServiceDown, message));
+            }
+        }
+    };
+
+    // 3. frpc 登录状态
+    match login_state(&app.state::<RecentLogs>().lines(&id)) {
+        Some(false) => {
+            let message = "frpc 未能登录服务器或启动隧道，请查看隧道日志".to_string();
+            v.hop("login", Some(false), message.clone(), None);
+            return Ok(v.fail(VerifyFailure::NotLoggedIn, message));
+        }
+        Some(true) => v.hop("login", Some(true), "frpc 已登录服务器".to_string(), None),
+        None => v.hop("login", None, "日志中未找到登录结果".to_string(), None),
+    }
+
+    // 4. HTTP / HTTPS：请求绑定的域名
+    if proxy_type == "http" || proxy_type == "https" {
+        let domain = match proxy.domain.as_deref().map(parse_domains) {
+            Some(domains) if !domains.is_empty() => domains[0].clone(),
+            _ => return Err("隧道未绑定域名".to_string()),
+        };
+        if let Err(e) = ping::resolve(&domain, 80, AddressFamily::Any).await {
+            let message = format!("域名 {} 无法解析: {}", domain, e);
+            v.hop("dns", Some(false), message.clone(), None);
+            return Ok(v.fail(VerifyFailure::DnsNotResolving, message));
+        }
+        v.hop("dns", Some(true), format!("{} 解析成功", domain), None);
+
+        // HTTPS 隧道的本地服务通常也是 HTTPS；开启自动 TLS 时由 frpc 终止 TLS，本地为 HTTP
+        let local_host = url_host(&proxy.local_ip);
+        let mut local_status = http_status(&format!(
+            "{}://{}:{}/",
+            proxy_type, local_host, proxy.local_port
+        ))
+        .await
+        .ok()
+        .map(|(_, status)| status);
+        if local_status.is_none() && proxy_type == "https" {
+            local_status = http_status(&format!("http://{}:{}/", local_host, proxy.local_port))
+                .await
+                .ok()
+                .map(|(_, status)| status);
+        }
+        let mut url = format!("{}://{}", proxy_type, domain);
+        if proxy.remote_port > 0 && proxy.remote_port != 80 && proxy.remote_port != 443 {
+            url = format!("{}:{}", url, proxy.remote_port);
+        }
+        return Ok(match http_status(&format!("{}/", url)).await {
+            Ok((latency, status)) => {
+                // frps 在隧道无法连接本地服务时返回 404 / 502 等错误页
+                let matches = local_status.map_or(status < 500, |local| local == status);
+                let detail = match local_status {
+                    Some(local) => format!("{} 返回 {}，本地返回 {}", url, status, local),
+                    None => format!("{} 返回 {}", url, status),
+                };
+                v.hop("remote", Some(matches), detail.clone(), Some(latency));
+                if matches {
+                    VerifyReport {
+                        ok: true,
+                        failure: None,
+                        message: "隧道工作正常".to_string(),
+                        hops: v.hops,
+                    }
+                } else {
+                    v.fail(VerifyFailure::ServiceMismatch, detail)
+                }
+            }
+            Err(e) => {
+                let message = format!("请求 {} 失败: {}", url, e);
+                v.hop("remote", Some(false), message.clone(), None);
+                v.fail(VerifyFailure::RemotePortClosed, message)
+            }
+        });
+    }
+
+    // 5. TCP / UDP：连接公网地址
+    let (host, port) = match proxy
+        .connect_address
+        .as_deref()
+        .and_then(|address| split_address(address, proxy.remote_port))
+    {
+        Some(address) => address,
+        None => return Err("隧道缺少连接地址".to_string()),
+    };
+    let remote_addr = match ping::resolve(&host, port, AddressFamily::Any).await {
+        Ok((addrs, dns_ms)) => {
+            v.hop(
+                "dns",
+                Some(true),
+                format!("{} 解析成功", host),
+                Some(dns_ms),
+            );
+            addrs[0]
+        }
+        Err(e) => {
+            let message = format!("{} 无法解析: {}", host, e);
+            v.hop("dns", Some(false), message.clone(), None);
+            return Ok(v.fail(VerifyFailure::DnsNotResolving, message));
+        }
+    };
+
+    // UDP 没有连接过程，只能发送探测等待回复；没有回复时无法区分不通与服务不回复未知请求
+    if proxy_type == "udp" {
+        return Ok(
+            match probe_udp(remote_addr, &udp_probe_kind(proxy.local_port)).await {
+                Ok(latency) => {
+                    v.hop(
+                        "remote",
+                        Some(true),
+                        format!("{}:{} 回复了 UDP 探测", host, port),
+                        Some(latency),
+                    );
+                    VerifyReport {
+                        ok: true,
+                        failure: None,
+                        message: "隧道工作正常".to_string(),
+                        hops: v.hops,
+                    }
+                }
+                Err(e) if e.contains("ICMP") => {
+                    let message = format!("远程端口 {}:{} 不可达: {}", host, port, e);
+                    v.hop("remote", Some(false), message.clone(), None);
+                    v.fail(VerifyFailure::RemotePortClosed, message)
+                }
+                Err(e) => {
+                    let message = format!(
+                        "{}:{} 未回复 UDP 探测（{}），无法确认隧道是否可用，部分 UDP 服务不会回复未知请求",
+                        host, port, e
+                    );
+                    v.hop("remote", None, message.clone(), None);
+                    VerifyReport {
+                        ok: false,
+                        failure: None,
+                        message,
+                        hops: v.hops,
+                    }
+                }
+            },
+        );
+    }
+
+    let (latency, remote_banner) = match connect_and_read_banner(remote_addr).await {
+        Ok(result) => result,
+        Err(e) => {
+            let message = format!("远程端口 {}:{} 无法连接: {}", host, port, e);
+            v.hop("remote", Some(false), message.clone(), None);
+            return Ok(v.fail(VerifyFailure::RemotePortClosed, message));
+        }
+    };
+
+    // 比较欢迎信息：本地服务会主动发送时，远程应收到同一服务的欢迎信息
+    let mismatch = match (&local_banner, &remote_banner) {
+        (Some(local), Some(remote)) if !local.is_empty() => {
+            banner_signature(local) != banner_signature(remote)
+        }
+        (Some(local), None) => !local.is_empty(),
+        (_, Some(remote)) => remote.is_empty(),
+        _ => false,
+    };
+    if mismatch {
+        let message = match &remote_banner {
+            Some(remote) if remote.is_empty() => {
+                "远程端口接受连接后立即断开，frpc 可能无法访问本地服务".to_string()
+            }
+            None => "远程端口没有发送本地服务的欢迎信息".to_string(),
+            _ => "远程端口返回的内容与本地服务不一致".to_string(),
+        };
+        v.hop("remote", Some(false), message.clone(), Some(latency));
+        return Ok(v.fail(VerifyFailure::ServiceMismatch, message));
+    }
+
+    let detail = match local_banner.as_deref().map(first_line) {
+        Some(line) if !line.is_empty() => format!("{}:{} 返回 {}", host, port, line),
+        _ => format!("{}:{} 可以连接", host, port),
+    };
+    v.hop("remote", Some(true), detail, Some(latency));
+    Ok(VerifyReport {
+        ok: true,
+        failure: None,
+        message: "隧道工作正常".to_string(),
+        hops: v.hops,
+    })
+}
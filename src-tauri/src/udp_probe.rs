@@ -0,0 +1,339 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Instant;
+use tauri::command;
+use tokio::net::UdpSocket;
+
+use crate::ping::{self, AddressFamily, PingAttempt, PingOptions, PingStats};
+
+// 接收缓冲区大小，足够容纳常见服务的单个 UDP 响应
+const RECV_BUFFER_SIZE: usize = 4096;
+// 原始负载的最大长度
+const MAX_PAYLOAD_LEN: usize = 1400;
+
+// Bedrock（RakNet）离线消息标识
+const RAKNET_MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+const A2S_HEADER: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+// UDP 探测类型：原始负载或针对具体协议的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UdpProbeKind {
+    // 发送任意负载，收到任何回复即视为可达；payload_hex 优先于 payload
+    Raw {
+        payload: Option<String>,
+        payload_hex: Option<String>,
+    },
+    // DNS 查询，默认查询 example.com 的 A 记录
+    Dns {
+        name: Option<String>,
+    },
+    // Minecraft 基岩版 Unconnected Ping
+    Bedrock,
+    // Source 引擎 A2S_INFO
+    A2sInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UdpProbeReport {
+    pub success: bool,
+    pub message: String,
+    pub address: Option<String>,
+    pub dns_ms: Option<f64>,
+    #[serde(flatten)]
+    pub stats: PingStats,
+    pub attempts: Vec<PingAttempt>,
+    // 最后一次有效回复解析出的服务信息
+    pub reply: Option<serde_json::Value>,
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if hex.len() % 2 != 0 {
+        return Err("十六进制负载长度必须为偶数".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "无效的十六进制负载".to_string())
+        })
+        .collect()
+}
+
+fn dns_query(id: u16, name: &str) -> Result<Vec<u8>, String> {
+    let mut packet = Vec::with_capacity(32 + name.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    // 标准查询，期望递归
+    packet.extend_from_slice(&[0x01, 0x00]);
+    // QDCOUNT = 1，其余计数为 0
+    packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("无效的域名: {}", name));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    // QTYPE = A，QCLASS = IN
+    packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x01]);
+    Ok(packet)
+}
+
+fn parse_dns_reply(id: u16, reply: &[u8]) -> Option<serde_json::Value> {
+    if reply.len() < 12 || u16::from_be_bytes([reply[0], reply[1]]) != id || reply[2] & 0x80 == 0 {
+        return None;
+    }
+    let rcode = reply[3] & 0x0f;
+    Some(serde_json::json!({
+        "rcode": rcode,
+        "answers": u16::from_be_bytes([reply[6], reply[7]]),
+    }))
+}
+
+fn bedrock_ping(client_guid: u64) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(33);
+    packet.push(0x01);
+    packet.extend_from_slice(&(crate::session::now_secs() * 1000).to_be_bytes());
+    packet.extend_from_slice(&RAKNET_MAGIC);
+    packet.extend_from_slice(&client_guid.to_be_bytes());
+    packet
+}
+
+// Unconnected Pong：0x1c、时间、服务器 GUID、MAGIC、字符串长度与服务器信息
+fn parse_bedrock_pong(reply: &[u8]) -> Option<serde_json::Value> {
+    if reply.len() < 35 || reply[0] != 0x1c || reply[17..33] != RAKNET_MAGIC {
+        return None;
+    }
+    let len = u16::from_be_bytes([reply[33], reply[34]]) as usize;
+    let info = String::from_utf8_lossy(reply.get(35..35 + len)?).to_string();
+    // MCPE;MOTD;协议版本;游戏版本;在线人数;最大人数;...
+    let fields: Vec<&str> = info.split(';').collect();
+    Some(serde_json::json!({
+        "edition": fields.first(),
+        "motd": fields.get(1),
+        "protocol": fields.get(2).and_then(|p| p.parse::<u32>().ok()),
+        "version": fields.get(3),
+        "players": fields.get(4).and_then(|p| p.parse::<u32>().ok()),
+        "max_players": fields.get(5).and_then(|p| p.parse::<u32>().ok()),
+    }))
+}
+
+fn a2s_info_request(challenge: Option<[u8; 4]>) -> Vec<u8> {
+    let mut packet = A2S_HEADER.to_vec();
+    packet.push(0x54);
+    packet.extend_from_slice(b"Source Engine Query\0");
+    if let Some(challenge) = challenge {
+        packet.extend_from_slice(&challenge);
+    }
+    packet
+}
+
+fn read_cstr(data: &[u8], pos: &mut usize) -> Option<String> {
+    let end = data.get(*pos..)?.iter().position(|b| *b == 0)? + *pos;
+    let s = String::from_utf8_lossy(&data[*pos..end]).to_string();
+    *pos = end + 1;
+    Some(s)
+}
+
+// A2S_INFO 回复：0x49、协议版本、名称、地图、目录、游戏、AppID、玩家数、最大玩家数
+fn parse_a2s_info(reply: &[u8]) -> Option<serde_json::Value> {
+    if reply.len() < 6 || reply[..4] != A2S_HEADER || reply[4] != 0x49 {
+        return None;
+    }
+    let mut pos = 6;
+    let name = read_cstr(reply, &mut pos)?;
+    let map = read_cstr(reply, &mut pos)?;
+    let folder = read_cstr(reply, &mut pos)?;
+    let game = read_cstr(reply, &mut pos)?;
+    let players = reply.get(pos + 2).copied();
+    let max_players = reply.get(pos + 3).copied();
+    Some(serde_json::json!({
+        "name": name,
+        "map": map,
+        "folder": folder,
+        "game": game,
+        "players": players,
+        "max_players": max_players,
+    }))
+}
+
+async fn exchange(
+    socket: &UdpSocket,
+    packet: &[u8],
+    buf: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    socket.send(packet).await?;
+    socket.recv(buf).await
+}
+
+// 原始探测的负载，其他探测类型返回空
+pub fn raw_payload(probe: &UdpProbeKind) -> Result<Vec<u8>, String> {
+    let payload = match probe {
+        UdpProbeKind::Raw {
+            payload,
+            payload_hex,
+        } => match (payload_hex, payload) {
+            (Some(hex), _) => parse_hex(hex)?,
+            (None, Some(text)) => text.as_bytes().to_vec(),
+            (None, None) => return Err("缺少探测负载".to_string()),
+        },
+        _ => Vec::new(),
+    };
+    if payload.len() > MAX_PAYLOAD_LEN {
+        return Err(format!("探测负载不能超过 {} 字节", MAX_PAYLOAD_LEN));
+    }
+    Ok(payload)
+}
+
+// 发送一次探测并校验回复，返回往返耗时与解析出的服务信息
+pub async fn probe_once(
+    addr: SocketAddr,
+    kind: &UdpProbeKind,
+    raw_payload: &[u8],
+) -> Result<(f64, Option<serde_json::Value>), String> {
+    let bind: SocketAddr = if addr.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(bind).await.map_err(|e| e.to_string())?;
+    socket.connect(addr).await.map_err(|e| e.to_string())?;
+
+    let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+    let start = Instant::now();
+    let io_error = |e: std::io::Error| {
+        if e.kind() == std::io::ErrorKind::ConnectionRefused {
+            "端口不可达（ICMP Port Unreachable）".to_string()
+        } else {
+            e.to_string()
+        }
+    };
+
+    let reply = match kind {
+        UdpProbeKind::Raw { .. } => {
+            let len = exchange(&socket, raw_payload, &mut buf)
+                .await
+                .map_err(io_error)?;
+            Some(serde_json::json!({ "length": len }))
+        }
+        UdpProbeKind::Dns { name } => {
+            let id: u16 = rand::thread_rng().gen();
+            let query = dns_query(id, name.as_deref().unwrap_or("example.com"))?;
+            let len = exchange(&socket, &query, &mut buf)
+                .await
+                .map_err(io_error)?;
+            parse_dns_reply(id, &buf[..len])
+        }
+        UdpProbeKind::Bedrock => {
+            let guid: u64 = rand::thread_rng().gen();
+            let len = exchange(&socket, &bedrock_ping(guid), &mut buf)
+                .await
+                .map_err(io_error)?;
+            parse_bedrock_pong(&buf[..len])
+        }
+        UdpProbeKind::A2sInfo => {
+            let mut len = exchange(&socket, &a2s_info_request(None), &mut buf)
+                .await
+                .map_err(io_error)?;
+            // 新版服务端会先返回 S2C_CHALLENGE，需要带上挑战值重新请求
+            if len >= 9 && buf[..4] == A2S_HEADER && buf[4] == 0x41 {
+                let challenge = [buf[5], buf[6], buf[7], buf[8]];
+                len = exchange(&socket, &a2s_info_request(Some(challenge)), &mut buf)
+                    .await
+                    .map_err(io_error)?;
+            }
+            parse_a2s_info(&buf[..len])
+        }
+    };
+
+    let latency = start.elapsed().as_secs_f64() * 1000.0;
+    match reply {
+        Some(reply) => Ok((latency, Some(reply))),
+        None => Err("收到的回复不是预期的协议响应".to_string()),
+    }
+}
+
+fn failed_report(message: String, dns_ms: Option<f64>) -> UdpProbeReport {
+    UdpProbeReport {
+        success: false,
+        message,
+        address: None,
+        dns_ms,
+        stats: PingStats::default(),
+        attempts: Vec::new(),
+        reply: None,
+    }
+}
+
+// UDP 可达性探测：发送负载并等待回复，用于检查 UDP 隧道是否端到端可用
+#[command]
+pub async fn udp_probe(
+    host: String,
+    port: u16,
+    probe: UdpProbeKind,
+    count: Option<u32>,
+    interval_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+    family: Option<AddressFamily>,
+) -> Result<UdpProbeReport, String> {
+    let raw_payload = raw_payload(&probe)?;
+    let options = PingOptions::new(count, interval_ms, timeout_ms);
+    let (addrs, dns_ms) = match ping::resolve(&host, port, family.unwrap_or_default()).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Ok(failed_report(e, None)),
+    };
+    let addr = addrs[0];
+    log::debug!("udp_probe {} ({:?})", addr, probe);
+
+    let mut attempts = Vec::with_capacity(options.count as usize);
+    let mut last_reply = None;
+    for seq in 0..options.count {
+        if seq > 0 && !options.interval.is_zero() {
+            tokio::time::sleep(options.interval).await;
+        }
+        let result = tokio::time::timeout(options.timeout, probe_once(addr, &probe, &raw_payload))
+            .await
+            .unwrap_or_else(|_| Err(format!("等待回复超时（{}ms）", options.timeout.as_millis())));
+        attempts.push(match result {
+            Ok((latency, reply)) => {
+                last_reply = reply;
+                PingAttempt {
+                    seq,
+                    success: true,
+                    latency_ms: Some(latency),
+                    error: None,
+                }
+            }
+            Err(e) => PingAttempt {
+                seq,
+                success: false,
+                latency_ms: None,
+                error: Some(e),
+            },
+        });
+    }
+
+    let stats = PingStats::from_attempts(&attempts);
+    let message = match (stats.avg_ms, attempts.last()) {
+        (Some(avg), _) => format!(
+            "收到回复，平均往返 {:.0}ms，丢包率 {:.0}%",
+            avg, stats.loss_percent
+        ),
+        (None, Some(PingAttempt { error: Some(e), .. })) => format!("未收到有效回复: {}", e),
+        _ => "未收到有效回复".to_string(),
+    };
+
+    Ok(UdpProbeReport {
+        success: stats.received > 0,
+        message,
+        address: Some(addr.to_string()),
+        dns_ms: Some(dns_ms),
+        stats,
+        attempts,
+        reply: last_reply,
+    })
+}
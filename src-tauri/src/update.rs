@@ -1,189 +1,500 @@
 use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use tauri::AppHandle;
 use tauri::Emitter;
-use tauri::Listener;
-use tauri_plugin_updater::UpdaterExt;
+use tauri::Manager;
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+use tokio::sync::oneshot;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UpdateInfo {
     pub title: String,
     pub latest: String,
     pub msg: String,
+    // 本次检查使用的更新通道
+    pub channel: String,
+    // 从测试通道切回较稳定的通道时，提供的版本可能低于当前版本
+    pub downgrade: bool,
+    // 发布时间
+    pub pub_date: Option<String>,
+    // 当前平台安装包大小（字节），无法获取时为空
+    pub size: Option<u64>,
+    // 当前平台对应的目标，例如 windows-x86_64
+    pub target: String,
+    // 清单中各平台是否提供了安装包
+    pub platforms: BTreeMap<String, bool>,
 }
 
-// 检查更新并返回更新信息
-pub async fn check_update() -> Result<Option<UpdateInfo>, Box<dyn Error>> {
+// 清单中可能出现的平台目标
+const KNOWN_TARGETS: &[&str] = &[
+    "windows-x86_64",
+    "windows-i686",
+    "windows-aarch64",
+    "linux-x86_64",
+    "linux-aarch64",
+    "darwin-x86_64",
+    "darwin-aarch64",
+];
+
+// 更新流程的状态，通过 update-state 事件发送给前端
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum UpdatePhase {
+    Idle,
+    Checking,
+    Downloading {
+        bytes: u64,
+        total: Option<u64>,
+    },
+    Verifying,
+    Installing,
+    Done,
+    Failed {
+        reason: String,
+    },
+    // 检查更新失败（例如离线），与下载安装失败分开，前端不弹出失败提示
+    CheckFailed {
+        reason: String,
+    },
+    // 已下载完成，等待按计划安装
+    Staged {
+        version: String,
+        install: crate::update_schedule::InstallWhen,
+    },
+}
+
+#[derive(Default)]
+pub struct UpdateState {
+    // 最近一次检查到的更新，安装时使用同一个更新，避免与提示的版本不一致
+    pending: Mutex<Option<Update>>,
+    phase: Mutex<Option<UpdatePhase>>,
+    // 下载过程中用于取消的发送端
+    cancel: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+// 未知总大小时每下载这么多字节发送一次进度
+const PROGRESS_STEP_BYTES: u64 = 512 * 1024;
+
+// 更新状态并通知前端
+pub fn set_phase(app_handle: &AppHandle, phase: UpdatePhase) {
+    if let Some(state) = app_handle.try_state::<UpdateState>() {
+        *state.phase.lock().unwrap() = Some(phase.clone());
+    }
+    let _ = app_handle.emit("update-state", &phase);
+}
+
+// 下载失败或取消时记录原因，并原样返回便于命令直接使用
+pub fn fail(app_handle: &AppHandle, reason: String) -> String {
+    log::warn!("{}", reason);
+    set_phase(
+        app_handle,
+        UpdatePhase::Failed {
+            reason: reason.clone(),
+        },
+    );
+    reason
+}
+
+// 检查更新失败时记录原因，不影响之后重新检查
+fn check_failed(app_handle: &AppHandle, reason: String) -> String {
+    log::warn!("{}", reason);
+    set_phase(
+        app_handle,
+        UpdatePhase::CheckFailed {
+            reason: reason.clone(),
+        },
+    );
+    reason
+}
+
+// 已下载等待安装的更新也视为进行中，避免被新的检查覆盖
+fn is_busy(phase: &Option<UpdatePhase>) -> bool {
+    matches!(
+        phase,
+        Some(UpdatePhase::Checking)
+            | Some(UpdatePhase::Downloading { .. })
+            | Some(UpdatePhase::Verifying)
+            | Some(UpdatePhase::Installing)
+            | Some(UpdatePhase::Staged { .. })
+    )
+}
+
+// 没有其他更新流程时进入指定状态，判断与设置在同一次加锁内完成
+fn try_begin(app_handle: &AppHandle, next: UpdatePhase) -> bool {
+    {
+        let state = app_handle.state::<UpdateState>();
+        let mut phase = state.phase.lock().unwrap();
+        if is_busy(&phase) {
+            return false;
+        }
+        *phase = Some(next.clone());
+    }
+    let _ = app_handle.emit("update-state", &next);
+    true
+}
+
+// 更新源地址，正式版通道保持原地址不变
+const UPDATE_ENDPOINT: &str = "https://api.zyghit.cn/updater/ofcpl";
+
+// 可选的更新通道，按稳定程度从高到低排列
+pub const UPDATE_CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+// 规范化更新通道名称，未知的通道按正式版处理
+pub fn normalize_channel(channel: Option<&str>) -> &'static str {
+    let channel = channel.unwrap_or(DEFAULT_CHANNEL).trim().to_lowercase();
+    UPDATE_CHANNELS
+        .iter()
+        .find(|c| **c == channel)
+        .copied()
+        .unwrap_or(DEFAULT_CHANNEL)
+}
+
+// 指定通道对应的更新源
+pub fn channel_endpoint(channel: &str) -> String {
+    if channel == DEFAULT_CHANNEL {
+        UPDATE_ENDPOINT.to_string()
+    } else {
+        format!("{}?channel={}", UPDATE_ENDPOINT, channel)
+    }
+}
+
+// 通道的稳定程度，数值越大越不稳定
+fn channel_rank(channel: &str) -> usize {
+    UPDATE_CHANNELS
+        .iter()
+        .position(|c| *c == channel)
+        .unwrap_or(0)
+}
+
+// 根据先行版本号推断版本所属通道，例如 0.7.0-beta.1 属于 beta
+fn version_channel(version: &str) -> &'static str {
+    let pre = version
+        .split('+')
+        .next()
+        .unwrap_or("")
+        .split_once('-')
+        .map(|(_, pre)| pre.to_lowercase());
+    match pre {
+        None => "stable",
+        Some(pre) if pre.starts_with("beta") || pre.starts_with("rc") => "beta",
+        Some(_) => "nightly",
+    }
+}
+
+// 判断是否应当提供远程版本：返回 None 表示无需更新，Some(true) 表示降级安装
+pub fn should_offer(current: &str, remote: &str, channel: &str) -> Option<bool> {
+    if compare_versions(current, remote) {
+        return Some(false);
+    }
+    // 当前安装的是比所选通道更不稳定的版本（例如从 beta 切回 stable），
+    // 此时允许安装该通道的最新版本，即使版本号更低
+    let same = crate::version::compare(current, remote) == Some(cmp::Ordering::Equal);
+    if !same && channel_rank(version_channel(current)) > channel_rank(channel) {
+        return Some(true);
+    }
+    None
+}
+
+// 按所选通道构建更新器，清单地址与版本比较规则都跟随通道
+fn build_updater(app_handle: &AppHandle, channel: &'static str) -> Result<Updater, String> {
+    let endpoint = reqwest::Url::parse(&channel_endpoint(channel)).map_err(|e| e.to_string())?;
+    app_handle
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .map_err(|e| e.to_string())?
+        .version_comparator(move |current, remote| {
+            should_offer(&current.to_string(), &remote.version.to_string(), channel).is_some()
+        })
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// 读取清单中各平台的安装包是否存在
+fn platform_availability(raw_json: &serde_json::Value) -> BTreeMap<String, bool> {
+    let mut platforms: BTreeMap<String, bool> = KNOWN_TARGETS
+        .iter()
+        .map(|target| (target.to_string(), false))
+        .collect();
+    if let Some(entries) = raw_json.get("platforms").and_then(|p| p.as_object()) {
+        for (target, entry) in entries {
+            let available = entry
+                .get("url")
+                .and_then(|u| u.as_str())
+                .map_or(false, |u| !u.is_empty());
+            platforms.insert(target.clone(), available);
+        }
+    }
+    platforms
+}
+
+// 查询安装包大小的超时，超时后不显示大小
+const SIZE_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 查询安装包大小共用的 HTTP 客户端
+fn size_client() -> Option<&'static reqwest::Client> {
+    static CLIENT: OnceLock<Option<reqwest::Client>> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            reqwest::Client::builder()
+                .timeout(SIZE_REQUEST_TIMEOUT)
+                .build()
+                .ok()
+        })
+        .as_ref()
+}
+
+// 通过 HEAD 请求获取安装包大小
+async fn fetch_download_size(url: &reqwest::Url) -> Option<u64> {
+    let response = size_client()?.head(url.clone()).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+// 按通道查询可用的更新，不修改更新状态，用于恢复重启前已下载的更新
+pub async fn find_update(
+    app_handle: &AppHandle,
+    channel: &'static str,
+) -> Result<Option<Update>, String> {
+    build_updater(app_handle, channel)?
+        .check()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 检查更新并返回更新信息：与安装使用同一份清单，检查到的更新会被保存，安装时直接使用
+pub async fn check_update(
+    app_handle: &AppHandle,
+    channel: &'static str,
+) -> Result<Option<UpdateInfo>, String> {
     // 获取当前版本
     let current_version = env!("CARGO_PKG_VERSION").to_string();
-    println!("当前版本: {}", current_version);
-    
-    // 使用 Tauri 配置的更新源
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.zyghit.cn/updater/ofcpl")
-        .send()
-        .await?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API 请求失败: {}", response.status()).into());
+    log::info!("当前版本: {}，更新通道: {}", current_version, channel);
+
+    // 先构建更新器，构建失败时不进入检查状态
+    let updater = build_updater(app_handle, channel)?;
+
+    let state = app_handle.state::<UpdateState>();
+    if !try_begin(app_handle, UpdatePhase::Checking) {
+        return Err("正在检查或安装更新，请稍后再试".to_string());
     }
-    
-    let update_data: serde_json::Value = response.json().await?;
-    
-    // 解析更新信息
-    if let Some(version) = update_data.get("version").and_then(|v| v.as_str()) {
-        println!("服务器版本: {}", version);
-        
-        // 比较版本号
-        if compare_versions(&current_version, version) {
-            let notes = update_data.get("notes").and_then(|n| n.as_str()).unwrap_or("");
-            
-            // 构建更新信息
-            let update_info = UpdateInfo {
-                title: "发现新版本".to_string(),
-                latest: version.to_string(),
-                msg: notes.to_string(),
-            };
-            
-            return Ok(Some(update_info));
+
+    let update = match updater.check().await {
+        Ok(update) => update,
+        Err(e) => return Err(check_failed(app_handle, format!("检查更新失败: {}", e))),
+    };
+
+    let update = match update {
+        Some(update) => update,
+        None => {
+            // 没有更新或版本相同
+            state.pending.lock().unwrap().take();
+            set_phase(app_handle, UpdatePhase::Idle);
+            return Ok(None);
         }
-    }
-    
-    // 没有更新或版本相同
-    Ok(None)
+    };
+    log::info!("服务器版本: {}", update.version);
+
+    // 安装包大小查询完成后才结束检查状态，避免期间开始安装
+    let size = fetch_download_size(&update.download_url).await;
+    let downgrade = should_offer(&current_version, &update.version, channel).unwrap_or(false);
+    let update_info = UpdateInfo {
+        title: if downgrade {
+            format!("切换到{}通道", channel)
+        } else {
+            "发现新版本".to_string()
+        },
+        latest: update.version.clone(),
+        msg: update.body.clone().unwrap_or_default(),
+        channel: channel.to_string(),
+        downgrade,
+        pub_date: update.date.map(|date| date.to_string()),
+        size,
+        target: update.target.clone(),
+        platforms: platform_availability(&update.raw_json),
+    };
+
+    *state.pending.lock().unwrap() = Some(update);
+    set_phase(app_handle, UpdatePhase::Idle);
+    Ok(Some(update_info))
 }
 
-// 版本比较函数：如果远程版本更新则返回 true
+// 版本比较函数：按 SemVer 2.0 优先级判断远程版本是否更新
 fn compare_versions(current: &str, remote: &str) -> bool {
-    // 移除可能的 'v' 前缀
-    let current = current.trim_start_matches('v');
-    let remote = remote.trim_start_matches('v');
-    
-    // 将版本号分割为组件
-    let current_parts: Vec<&str> = current.split('.').collect();
-    let remote_parts: Vec<&str> = remote.split('.').collect();
-    
-    // 比较主要版本号
-    for i in 0..std::cmp::min(current_parts.len(), remote_parts.len()) {
-        let current_part = current_parts[i].parse::<u32>().unwrap_or(0);
-        let remote_part = remote_parts[i].parse::<u32>().unwrap_or(0);
-        
-        if remote_part > current_part {
-            return true;
-        } else if remote_part < current_part {
-            return false;
+    match crate::version::compare(current, remote) {
+        Some(ordering) => ordering == cmp::Ordering::Less,
+        None => {
+            log::warn!("无法解析版本号: 当前 {}, 远程 {}", current, remote);
+            false
         }
     }
-    
-    // 如果前面的版本号都相同，但远程版本有更多的组件，则认为远程版本更新
-    remote_parts.len() > current_parts.len()
 }
 
-#[tauri::command]
-// 下载并安装更新
-pub async fn download_and_install_update(app_handle: AppHandle) -> Result<(), String> {
-    // 使用 Tauri 的更新器 API
-    let updater = app_handle.updater().map_err(|e| e.to_string())?;
-    
-    // 检查更新
-    let update = updater.check().await.map_err(|e| e.to_string())?;
-    
-    if let Some(update) = update {
-        // 设置进度监听器
-        let app_handle_clone = app_handle.clone();
-        
-        // 监听更新状态事件
-        app_handle.listen("tauri://update-status", move |event| {
-            println!("收到更新状态事件: {}", event.payload());
-            
-            if let Ok(status) = serde_json::from_str::<serde_json::Value>(event.payload()) {
-                // 直接发送原始状态，便于调试
-                let _ = app_handle_clone.emit("update-raw-status", event.payload());
-                
-                // 处理不同状态
-                if let Some(status_str) = status.get("status").and_then(|s| s.as_str()) {
-                    match status_str {
-                        "PENDING" => {
-                            let _ = app_handle_clone.emit("update-status", "正在准备更新...");
-                        },
-                        "DOWNLOADING" => {
-                            if let Some(progress) = status.get("progress").and_then(|p| p.as_f64()) {
-                                let _ = app_handle_clone.emit("update-progress", format!("{:.1}%", progress * 100.0));
-                            } else {
-                                let _ = app_handle_clone.emit("update-status", "正在下载更新...");
-                            }
-                        },
-                        "DOWNLOADED" => {
-                            let _ = app_handle_clone.emit("update-status", "下载完成，准备安装...");
-                        },
-                        _ if status_str.contains("ERROR") => {
-                            if let Some(error) = status.get("error").and_then(|e| e.as_str()) {
-                                let _ = app_handle_clone.emit("update-error", error);
-                            } else {
-                                let _ = app_handle_clone.emit("update-error", "更新过程中发生未知错误");
-                            }
-                        },
-                        _ => {
-                            let _ = app_handle_clone.emit("update-status", format!("更新状态: {}", status_str));
-                        }
-                    }
-                }
-            } else {
-                println!("无法解析更新状态: {}", event.payload());
-            }
-        });
-        
-        // 开始下载并安装更新
-        println!("开始下载更新...");
-        
-        // 使用原子计数器跟踪下载进度
-        let downloaded_size = AtomicUsize::new(0);
-        let app_handle_for_download = app_handle.clone();
-        
-        match update.download(
-            move |chunk_size, total_size| {
-                // 下载进度回调
-                let current_size = downloaded_size.fetch_add(chunk_size, Ordering::SeqCst) + chunk_size;
-                if let Some(total) = total_size {
-                    let progress = (current_size as f64 / total as f64) * 100.0;
-                    println!("下载进度: {:.2}% ({} / {})", progress, current_size, total);
-                    let _ = app_handle_for_download.emit("update-progress", format!("{:.1}%", progress));
-                } else {
-                    println!("接收到 {} 字节", chunk_size);
-                }
-            },
-            || {
-                // 下载完成回调
-                println!("下载完成");
-            }
-        ).await {
-            Ok(bytes) => {
-                println!("更新下载完成，准备安装");
-                let _ = app_handle.emit("update-status", "下载完成，准备安装...");
-                
-                // 安装更新
-                match update.install(bytes) {
-                    Ok(_) => {
-                        println!("更新安装成功");
-                        let _ = app_handle.emit("update-status", "更新安装成功，即将重启应用...");
-                        Ok(())
+// 下载最近一次检查到的更新，传入 version 时校验与提示的版本一致，返回更新与已校验的安装包
+pub async fn download_update(
+    app_handle: &AppHandle,
+    version: Option<String>,
+) -> Result<(Update, Vec<u8>), String> {
+    let mut update = app_handle
+        .state::<UpdateState>()
+        .pending
+        .lock()
+        .unwrap()
+        .take();
+
+    // 尚未检查过更新时按当前通道检查一次
+    if update.is_none() {
+        let config = crate::load_config()?;
+        let channel = normalize_channel(config.update_channel.as_deref());
+        check_update(app_handle, channel).await?;
+        update = app_handle
+            .state::<UpdateState>()
+            .pending
+            .lock()
+            .unwrap()
+            .take();
+    }
+
+    if let (Some(expected), Some(found)) = (version.as_ref(), update.as_ref()) {
+        if expected.trim_start_matches('v') != found.version.trim_start_matches('v') {
+            return Err(format!(
+                "更新版本已变化（提示版本 {}，当前可用版本 {}），请重新检查更新",
+                expected, found.version
+            ));
+        }
+    }
+
+    let update = update.ok_or("没有可用的更新")?;
+
+    let started = try_begin(
+        app_handle,
+        UpdatePhase::Downloading {
+            bytes: 0,
+            total: None,
+        },
+    );
+    let state = app_handle.state::<UpdateState>();
+    if !started {
+        // 放回更新，避免正在进行的流程结束后无法再次安装
+        state.pending.lock().unwrap().get_or_insert(update);
+        return Err("更新正在进行中".to_string());
+    }
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    *state.cancel.lock().unwrap() = Some(cancel_tx);
+    log::info!("开始下载更新: {}", update.version);
+
+    let mut downloaded: u64 = 0;
+    let mut last_emitted: u64 = 0;
+    let progress_handle = app_handle.clone();
+    let finish_handle = app_handle.clone();
+    let download = update.download(
+        move |chunk_size, total| {
+            downloaded += chunk_size as u64;
+            // 按 1%（或未知总大小时按固定字节数）节流，避免事件过多
+            let step = total.map_or(PROGRESS_STEP_BYTES, |total| (total / 100).max(1));
+            if downloaded - last_emitted >= step || Some(downloaded) == total {
+                last_emitted = downloaded;
+                set_phase(
+                    &progress_handle,
+                    UpdatePhase::Downloading {
+                        bytes: downloaded,
+                        total,
                     },
-                    Err(e) => {
-                        println!("更新安装失败: {}", e);
-                        let _ = app_handle.emit("update-error", format!("更新安装失败: {}", e));
-                        Err(e.to_string())
-                    }
-                }
-            },
-            Err(e) => {
-                println!("更新下载失败: {}", e);
-                let _ = app_handle.emit("update-error", format!("更新下载失败: {}", e));
-                Err(e.to_string())
+                );
             }
+        },
+        move || {
+            // 下载完成后插件会校验安装包签名
+            set_phase(&finish_handle, UpdatePhase::Verifying);
+        },
+    );
+
+    let result = tokio::select! {
+        result = download => Some(result),
+        _ = cancel_rx => None,
+    };
+    app_handle
+        .state::<UpdateState>()
+        .cancel
+        .lock()
+        .unwrap()
+        .take();
+
+    let bytes = match result {
+        Some(Ok(bytes)) => bytes,
+        Some(Err(e)) => return Err(fail(app_handle, format!("更新下载失败: {}", e))),
+        None => {
+            // 取消后保留本次检查到的更新，可以直接重新下载
+            app_handle
+                .state::<UpdateState>()
+                .pending
+                .lock()
+                .unwrap()
+                .get_or_insert(update);
+            return Err(fail(app_handle, "更新下载已取消".to_string()));
         }
-    } else {
-        Err("没有可用的更新".to_string())
+    };
+
+    Ok((update, bytes))
+}
+
+// 安装已下载的更新
+pub fn install_downloaded(
+    app_handle: &AppHandle,
+    update: &Update,
+    bytes: Vec<u8>,
+) -> Result<(), String> {
+    set_phase(app_handle, UpdatePhase::Installing);
+    match update.install(bytes) {
+        Ok(_) => {
+            log::info!("更新安装成功: {}", update.version);
+            set_phase(app_handle, UpdatePhase::Done);
+            Ok(())
+        }
+        Err(e) => Err(fail(app_handle, format!("更新安装失败: {}", e))),
+    }
+}
+
+#[tauri::command]
+// 下载并立即安装更新
+pub async fn download_and_install_update(
+    app_handle: AppHandle,
+    version: Option<String>,
+) -> Result<(), String> {
+    let (update, bytes) = download_update(&app_handle, version).await?;
+    install_downloaded(&app_handle, &update, bytes)
+}
+
+// 查询当前更新状态，前端页面重新打开时用于恢复进度显示
+#[tauri::command]
+pub fn get_update_state(state: tauri::State<'_, UpdateState>) -> UpdatePhase {
+    state
+        .phase
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or(UpdatePhase::Idle)
+}
+
+// 取消正在进行的下载，安装开始后无法取消
+#[tauri::command]
+pub fn cancel_update_download(state: tauri::State<'_, UpdateState>) -> Result<(), String> {
+    match state.cancel.lock().unwrap().take() {
+        Some(cancel_tx) => {
+            let _ = cancel_tx.send(());
+            Ok(())
+        }
+        None => Err("当前没有正在下载的更新".to_string()),
     }
 }
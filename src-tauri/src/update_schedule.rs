@@ -0,0 +1,419 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_updater::Update;
+
+use crate::health::{self, HealthConfig};
+use crate::update::{self, UpdatePhase};
+
+// 计划任务检查间隔
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// 已下载更新的安装时机
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum InstallWhen {
+    // 立即安装
+    Now,
+    // 下次退出启动器时安装
+    OnQuit,
+    // 在指定时间（Unix 秒）安装，安装后重新启动正在运行的隧道
+    At { time: u64 },
+    // 没有隧道运行时安装
+    WhenIdle,
+}
+
+// 持久化的待安装更新，安装包保存在同目录下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpdate {
+    pub version: String,
+    pub channel: String,
+    pub install: InstallWhen,
+    pub downloaded_at: u64,
+    // 从较不稳定的通道切回时安装的较低版本
+    #[serde(default)]
+    pub downgrade: bool,
+    // 下载更新时正在运行的版本
+    #[serde(default)]
+    pub from_version: String,
+    file: String,
+}
+
+// 安装更新前正在运行的隧道，重启后按原账户重新启动并恢复健康检查；
+// 用户密钥不写入文件，重启后通过账户会话重新获取
+#[derive(Debug, Serialize, Deserialize)]
+struct RelaunchTunnel {
+    id: String,
+    tunnel_id: String,
+    profile_id: Option<String>,
+    #[serde(default)]
+    health: Option<HealthConfig>,
+}
+
+// 已下载完成、等待安装的更新；重启后通过重新检查更新恢复
+#[derive(Default)]
+pub struct ScheduledUpdateState(Mutex<Option<(PendingUpdate, Update)>>);
+
+fn updates_dir() -> PathBuf {
+    crate::get_app_dir().join("updates")
+}
+
+fn pending_path() -> PathBuf {
+    updates_dir().join("pending.json")
+}
+
+fn relaunch_path() -> PathBuf {
+    updates_dir().join("relaunch.json")
+}
+
+// 安装包的签名保存在同目录下，安装前重新校验
+fn signature_path(pending: &PendingUpdate) -> PathBuf {
+    updates_dir().join(format!("{}.sig", pending.file))
+}
+
+fn load_pending() -> Option<PendingUpdate> {
+    let content = fs::read_to_string(pending_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// 删除待安装更新及其安装包
+fn discard_pending() {
+    if let Some(pending) = load_pending() {
+        let _ = fs::remove_file(updates_dir().join(&pending.file));
+        let _ = fs::remove_file(signature_path(&pending));
+    }
+    let _ = fs::remove_file(pending_path());
+}
+
+fn save_pending(pending: &PendingUpdate, bytes: &[u8], signature: &str) -> Result<(), String> {
+    discard_pending();
+    crate::write_private_file(&updates_dir().join(&pending.file), bytes)
+        .map_err(|e| format!("保存更新安装包失败: {}", e))?;
+    crate::write_private_file(&signature_path(pending), signature.as_bytes())
+        .map_err(|e| format!("保存更新签名失败: {}", e))?;
+    let content = serde_json::to_vec_pretty(pending).map_err(|e| e.to_string())?;
+    crate::write_private_file(&pending_path(), &content)
+        .map_err(|e| format!("保存待安装更新失败: {}", e))
+}
+
+fn set_staged(app: &AppHandle, pending: &PendingUpdate) {
+    update::set_phase(
+        app,
+        UpdatePhase::Staged {
+            version: pending.version.clone(),
+            install: pending.install.clone(),
+        },
+    );
+}
+
+// 记录正在运行的隧道并停止它们，安装完成后由新版本重新启动
+fn stop_tunnels_for_relaunch(app: &AppHandle) -> Result<(), String> {
+    let processes = app.state::<crate::FrpcProcesses>();
+    // 先取出进程再结束，避免结束进程期间一直持有锁
+    let drained: Vec<(String, crate::ProcessInfo)> = processes
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .drain()
+        .collect();
+    if drained.is_empty() {
+        return Ok(());
+    }
+
+    let tunnels: Vec<RelaunchTunnel> = drained
+        .iter()
+        .map(|(id, info)| RelaunchTunnel {
+            id: id.clone(),
+            tunnel_id: info.tunnel_id.clone(),
+            profile_id: info.profile_id.clone(),
+            health: health::watched_config(app, id),
+        })
+        .collect();
+    let saved = serde_json::to_vec(&tunnels)
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            crate::write_private_file(&relaunch_path(), &content)
+                .map_err(|e| format!("保存隧道列表失败: {}", e))
+        });
+    if let Err(e) = saved {
+        // 无法记录时放回进程，不停止隧道
+        if let Ok(mut map) = processes.0.lock() {
+            map.extend(drained);
+        }
+        return Err(e);
+    }
+
+    for (_, process_info) in drained {
+        crate::terminate_process(process_info);
+    }
+    Ok(())
+}
+
+// 用配置中的更新公钥校验安装包签名，与 updater 插件下载时的校验方式一致
+fn verify_signature(app: &AppHandle, bytes: &[u8], signature: &str) -> Result<(), String> {
+    let decode = |value: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(value.trim())
+            .ok()
+            .and_then(|raw| String::from_utf8(raw).ok())
+    };
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater["pubkey"].as_str())
+        .and_then(decode)
+        .ok_or("更新公钥无效")?;
+    let public_key =
+        minisign_verify::PublicKey::decode(&pubkey).map_err(|e| format!("更新公钥无效: {}", e))?;
+    let signature = decode(signature)
+        .and_then(|signature| minisign_verify::Signature::decode(&signature).ok())
+        .ok_or("更新签名无效")?;
+    public_key
+        .verify(bytes, &signature, true)
+        .map_err(|e| format!("更新安装包签名校验失败: {}", e))
+}
+
+// 安装已下载的更新；relaunch 为 true 时停止并在重启后恢复正在运行的隧道
+fn install_staged(app: &AppHandle, relaunch: bool) -> Result<(), String> {
+    let (pending, update) = app
+        .state::<ScheduledUpdateState>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("没有待安装的更新")?;
+
+    let bytes = fs::read(updates_dir().join(&pending.file))
+        .map_err(|e| format!("读取更新安装包失败: {}", e))?;
+    // 安装包在磁盘上保存期间可能被替换，按下载时的签名重新校验
+    let verified = fs::read_to_string(signature_path(&pending))
+        .map_err(|e| format!("读取更新签名失败: {}", e))
+        .and_then(|signature| verify_signature(app, &bytes, &signature));
+    if let Err(e) = verified {
+        discard_pending();
+        return Err(update::fail(app, e));
+    }
+
+    if relaunch {
+        stop_tunnels_for_relaunch(app)?;
+    }
+    log::info!("安装已下载的更新: {}", pending.version);
+    let result = update::install_downloaded(app, &update, bytes);
+    match &result {
+        Ok(_) => discard_pending(),
+        // 安装失败时保留安装包，下次启动时恢复为待安装状态重试；隧道立即恢复
+        Err(_) if relaunch => {
+            tauri::async_runtime::spawn(relaunch_tunnels(app.clone()));
+        }
+        Err(_) => {}
+    }
+    result
+}
+
+// 启动时重新启动更新前正在运行的隧道，隧道配置按账户通过已保存的会话重新获取
+pub async fn relaunch_tunnels(app: AppHandle) {
+    let path = relaunch_path();
+    let tunnels: Vec<RelaunchTunnel> = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => return,
+    };
+    let _ = fs::remove_file(&path);
+
+    let mut started = Vec::new();
+    for tunnel in tunnels {
+        let processes = app.state::<crate::FrpcProcesses>();
+        match crate::launch_tunnel(
+            &app,
+            &processes,
+            tunnel.id.clone(),
+            tunnel.tunnel_id,
+            tunnel.profile_id,
+        )
+        .await
+        {
+            Ok(_) => {
+                if let Some(config) = tunnel.health {
+                    health::watch(&app, tunnel.id.clone(), config, None);
+                }
+                started.push(tunnel.id);
+            }
+            Err(e) => log::warn!("重新启动隧道 {} 失败: {}", tunnel.id, e),
+        }
+    }
+    if !started.is_empty() {
+        let _ = app.emit("tunnels-relaunched", &started);
+    }
+}
+
+// 启动时恢复上次下载的更新：重新检查同一通道，版本一致时才继续等待安装
+pub async fn restore_pending(app: AppHandle) {
+    let pending = match load_pending() {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    // 降级更新以下载时的版本为准，运行的版本已变化说明已安装过其他版本
+    let current = env!("CARGO_PKG_VERSION");
+    let outdated = if pending.downgrade {
+        current != pending.from_version
+    } else {
+        crate::version::compare(current, &pending.version)
+            .map_or(true, |ordering| ordering != std::cmp::Ordering::Less)
+    };
+    if outdated || !updates_dir().join(&pending.file).exists() || !signature_path(&pending).exists()
+    {
+        discard_pending();
+        return;
+    }
+
+    let channel = update::normalize_channel(Some(&pending.channel));
+    match update::find_update(&app, channel).await {
+        Ok(Some(update)) if update.version == pending.version => {
+            set_staged(&app, &pending);
+            *app.state::<ScheduledUpdateState>().0.lock().unwrap() = Some((pending, update));
+        }
+        Ok(_) => {
+            log::info!("待安装的更新 {} 已不可用，已丢弃", pending.version);
+            discard_pending();
+        }
+        // 网络错误时保留安装包，下次启动再尝试
+        Err(e) => log::warn!("恢复待安装更新失败: {}", e),
+    }
+}
+
+// 后台检查计划安装的时间与空闲条件
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+
+            let install = app
+                .state::<ScheduledUpdateState>()
+                .0
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|(pending, _)| pending.install.clone());
+            let due = match install {
+                Some(InstallWhen::At { time }) => crate::session::now_secs() >= time,
+                Some(InstallWhen::WhenIdle) => app
+                    .state::<crate::FrpcProcesses>()
+                    .0
+                    .lock()
+                    .map_or(false, |map| map.is_empty()),
+                _ => false,
+            };
+            if !due {
+                continue;
+            }
+
+            if install_staged(&app, true).is_ok() {
+                // Windows 安装程序会自行退出并重启启动器，其他平台需要手动重启
+                app.restart();
+            }
+        }
+    });
+}
+
+// 退出启动器时安装计划在退出时安装的更新
+pub fn install_on_exit(app: &AppHandle) {
+    let on_quit = app
+        .state::<ScheduledUpdateState>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |(pending, _)| pending.install == InstallWhen::OnQuit);
+    if on_quit {
+        if let Err(e) = install_staged(app, false) {
+            log::error!("退出时安装更新失败: {}", e);
+        }
+    }
+}
+
+fn validate_install(install: &InstallWhen) -> Result<(), String> {
+    if let InstallWhen::At { time } = install {
+        if *time <= crate::session::now_secs() {
+            return Err("计划安装时间必须晚于当前时间".to_string());
+        }
+    }
+    Ok(())
+}
+
+// 下载更新并按指定时机安装
+#[command]
+pub async fn schedule_update(
+    app: AppHandle,
+    install: InstallWhen,
+    version: Option<String>,
+) -> Result<(), String> {
+    validate_install(&install)?;
+    if install == InstallWhen::Now {
+        return update::download_and_install_update(app, version).await;
+    }
+
+    let config = crate::load_config()?;
+    let channel = update::normalize_channel(config.update_channel.as_deref());
+    let (update, bytes) = update::download_update(&app, version).await?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let pending = PendingUpdate {
+        version: update.version.clone(),
+        channel: channel.to_string(),
+        install,
+        downloaded_at: crate::session::now_secs(),
+        downgrade: update::should_offer(current, &update.version, channel) == Some(true),
+        from_version: current.to_string(),
+        file: format!("update-{}.bin", update.version),
+    };
+    save_pending(&pending, &bytes, &update.signature)?;
+    set_staged(&app, &pending);
+    *app.state::<ScheduledUpdateState>().0.lock().unwrap() = Some((pending, update));
+    Ok(())
+}
+
+#[command]
+pub fn get_pending_update(state: State<'_, ScheduledUpdateState>) -> Option<PendingUpdate> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(pending, _)| pending.clone())
+}
+
+// 修改已下载更新的安装时机
+#[command]
+pub fn reschedule_update(
+    app: AppHandle,
+    state: State<'_, ScheduledUpdateState>,
+    install: InstallWhen,
+) -> Result<(), String> {
+    validate_install(&install)?;
+    if install == InstallWhen::Now {
+        install_staged(&app, true)?;
+        app.restart();
+    }
+    let mut guard = state.0.lock().unwrap();
+    let (pending, _) = guard.as_mut().ok_or("没有待安装的更新")?;
+    pending.install = install;
+    let content = serde_json::to_vec_pretty(&*pending).map_err(|e| e.to_string())?;
+    crate::write_private_file(&pending_path(), &content)
+        .map_err(|e| format!("保存待安装更新失败: {}", e))?;
+    set_staged(&app, pending);
+    Ok(())
+}
+
+// 放弃已下载的更新
+#[command]
+pub fn cancel_pending_update(app: AppHandle, state: State<'_, ScheduledUpdateState>) {
+    state.0.lock().unwrap().take();
+    discard_pending();
+    update::set_phase(&app, UpdatePhase::Idle);
+}
@@ -0,0 +1,220 @@
+use std::cmp::Ordering;
+
+// 先行版本号中的标识符，数字标识符总是低于字母标识符
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alpha(_)) => Ordering::Less,
+            (Identifier::Alpha(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alpha(a), Identifier::Alpha(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// SemVer 2.0 版本号，构建元数据（+ 之后的部分）不参与比较
+#[derive(Debug, Clone)]
+pub struct Version {
+    numbers: Vec<u64>,
+    pre: Vec<Identifier>,
+}
+
+impl Version {
+    // 解析版本号，允许 v 前缀以及 1.0 这样省略的写法（缺少的部分视为 0）
+    pub fn parse(input: &str) -> Option<Version> {
+        let input = input.trim();
+        let input = input
+            .strip_prefix('v')
+            .or_else(|| input.strip_prefix('V'))
+            .unwrap_or(input);
+        let without_build = input.split('+').next().unwrap_or("");
+        let (core, pre) = match without_build.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (without_build, None),
+        };
+
+        let numbers = core
+            .split('.')
+            .map(|part| {
+                if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+                    None
+                } else {
+                    part.parse::<u64>().ok()
+                }
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|part| {
+                    if part.is_empty()
+                        || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                    {
+                        None
+                    } else if part.chars().all(|c| c.is_ascii_digit()) {
+                        part.parse::<u64>().ok().map(Identifier::Numeric)
+                    } else {
+                        Some(Identifier::Alpha(part.to_string()))
+                    }
+                })
+                .collect::<Option<Vec<Identifier>>>()?,
+            None => Vec::new(),
+        };
+
+        Some(Version { numbers, pre })
+    }
+
+    // 从任意字符串中提取第一个形如 x.y.z 的版本号，例如 frpc -v 输出或 OF_0.61.1_4df06100_250122
+    pub fn extract(input: &str) -> Option<Version> {
+        let bytes = input.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            if bytes[start].is_ascii_digit()
+                && (start == 0 || !bytes[start - 1].is_ascii_alphanumeric())
+            {
+                let end = input[start..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .map(|len| start + len)
+                    .unwrap_or(input.len());
+                let candidate = input[start..end].trim_end_matches('.');
+                if candidate.contains('.') {
+                    if let Some(version) = Version::parse(candidate) {
+                        return Some(version);
+                    }
+                }
+                start = end.max(start + 1);
+            } else {
+                start += 1;
+            }
+        }
+        None
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 主版本号部分按数字逐段比较，长度不同时以 0 补齐，因此 1.0 与 1.0.0 相等
+        let len = self.numbers.len().max(other.numbers.len());
+        for i in 0..len {
+            let a = self.numbers.get(i).copied().unwrap_or(0);
+            let b = other.numbers.get(i).copied().unwrap_or(0);
+            match a.cmp(&b) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            }
+        }
+
+        // 带先行版本号的版本低于对应的正式版本
+        match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+// 比较两个版本号，任一无法解析时返回 None
+pub fn compare(a: &str, b: &str) -> Option<Ordering> {
+    Some(Version::parse(a)?.cmp(&Version::parse(b)?))
+}
+
+// 判断已安装的 frpc 是否无需更新：版本号相同但构建标识不同（例如 OF_0.61.1_4df06100_250122
+// 与 OF_0.61.1_5e2a9c10_250301）视为重新构建的版本，仍需下载
+pub fn is_up_to_date(current: &str, latest: &str) -> bool {
+    if current.trim() == latest.trim() {
+        return true;
+    }
+    match (Version::extract(current), Version::extract(latest)) {
+        (Some(current), Some(latest)) => current > latest,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prerelease_ordering() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(
+                compare(pair[0], pair[1]),
+                Some(Ordering::Less),
+                "{:?}",
+                pair
+            );
+        }
+    }
+
+    #[test]
+    fn parse_variants() {
+        assert_eq!(compare("v1.2", "1.2.0"), Some(Ordering::Equal));
+        assert_eq!(compare("1.2.3+build.5", "1.2.3"), Some(Ordering::Equal));
+        assert_eq!(compare("1.10.0", "1.9.9"), Some(Ordering::Greater));
+        assert_eq!(compare("1.0.0-", "1.0.0"), None);
+        assert_eq!(compare("abc", "1.0.0"), None);
+    }
+
+    #[test]
+    fn extract_of_format() {
+        let version = Version::extract("OF_0.61.1_4df06100_250122").unwrap();
+        assert_eq!(version, Version::parse("0.61.1").unwrap());
+        let version = Version::extract("frpc version 0.51.3").unwrap();
+        assert_eq!(version, Version::parse("0.51.3").unwrap());
+        assert!(Version::extract("OF_latest").is_none());
+    }
+
+    #[test]
+    fn up_to_date() {
+        let installed = "OF_0.61.1_4df06100_250122";
+        assert!(is_up_to_date(installed, installed));
+        // 版本号相同的重新构建仍需下载
+        assert!(!is_up_to_date(installed, "OF_0.61.1_5e2a9c10_250301"));
+        assert!(!is_up_to_date(installed, "OF_0.62.0_1a2b3c4d_250401"));
+        assert!(is_up_to_date("OF_0.62.0_1a2b3c4d_250401", installed));
+        // 无法解析时按字符串比较
+        assert!(!is_up_to_date("custom", "OF_0.61.1_4df06100_250122"));
+    }
+}